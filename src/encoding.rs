@@ -0,0 +1,802 @@
+//! Carve JSON strings that are not encoded in UTF-8.
+//!
+//! Java heaps, .NET heaps and Windows processes keep most of their strings
+//! in UTF-16, where `{"a":1}` is stored as `{\0"\0a\0"\0:\01\0}\0`, and some
+//! native runtimes, as well as Python, keep theirs in UTF-32 or Latin-1. The
+//! state machine of the carver works on UTF-8 bytes, so the byte stream is
+//! first transcoded to UTF-8, and the transcoded text is carved instead.
+//!
+//! A JSON string may start at any byte, so every code unit alignment is
+//! transcoded and carved separately. Carving an alignment is as slow as
+//! carving the whole byte stream, so we first look at the bytes around the
+//! opening brackets of the byte stream, and carve only the alignments that
+//! some of them may belong to.
+//!
+//! The positions of the hits are then mapped back to the byte stream. To keep
+//! this cheap for large dumps, we remember where every few thousand code
+//! units land in the transcoded text, and decode again from there.
+
+use std::char::REPLACEMENT_CHARACTER;
+use std::cmp::Reverse;
+use std::io;
+use std::thread;
+
+use crate::{
+    CHAR_LEFT_CURLY_BRACKET, CHAR_LEFT_SQUARE_BRACKET, CHAR_QUOT_MARK, Carver, Edit, Hit, Sink,
+};
+
+/// The number of code units between the positions that a transcoded text
+/// remembers.
+const CHECKPOINT_INTERVAL: usize = 4096;
+
+/// The number of code units that are transcoded and carved at a time.
+const TRANSCODE_CHUNK_SIZE: usize = 64 * CHECKPOINT_INTERVAL;
+
+/// The text encodings that the carver can carve JSON strings in (see
+/// [`Carver::parse_encoded`]).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Encoding {
+    /// UTF-8, which the carver handles natively.
+    Utf8,
+    /// UTF-16, little-endian, as in Windows, .NET and most Java heaps.
+    Utf16Le,
+    /// UTF-16, big-endian, as in Java class files and HPROF dumps.
+    Utf16Be,
+    /// UTF-32, little-endian, as in the wide strings of Linux programs and
+    /// the non-BMP strings of Python.
+    Utf32Le,
+    /// UTF-32, big-endian.
+    Utf32Be,
+    /// ISO-8859-1, as in the compact strings of Java and Python. Its first
+    /// 128 characters are the same as in UTF-8, so only JSON strings that are
+    /// not valid UTF-8 are carved as Latin-1.
+    Latin1,
+}
+
+impl Encoding {
+    /// The encodings that the carver can detect on its own, in the order
+    /// that they are preferred in.
+    pub const ALL: &'static [Encoding] = &[
+        Encoding::Utf8,
+        Encoding::Utf16Le,
+        Encoding::Utf16Be,
+        Encoding::Utf32Le,
+        Encoding::Utf32Be,
+        Encoding::Latin1,
+    ];
+
+    /// The name of the encoding, as it appears in reports.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Encoding::Utf8 => "utf-8",
+            Encoding::Utf16Le => "utf-16le",
+            Encoding::Utf16Be => "utf-16be",
+            Encoding::Utf32Le => "utf-32le",
+            Encoding::Utf32Be => "utf-32be",
+            Encoding::Latin1 => "latin-1",
+        }
+    }
+
+    /// The size of the code units of the encoding, in bytes.
+    fn unit_size(&self) -> usize {
+        match self {
+            Encoding::Utf8 | Encoding::Latin1 => 1,
+            Encoding::Utf16Le | Encoding::Utf16Be => 2,
+            Encoding::Utf32Le | Encoding::Utf32Be => 4,
+        }
+    }
+
+    /// Whether the ASCII character at position `pos` of `input` may be a
+    /// character of this encoding, in the alignment that starts at `offset`.
+    fn may_encode(&self, input: &[u8], pos: usize, offset: usize) -> bool {
+        // The code unit that the character belongs to, and the bytes of it
+        // that must be zero.
+        let (unit, zeros) = match self {
+            // No JSON string has a zero byte after a bracket or a quote.
+            Encoding::Utf8 | Encoding::Latin1 => {
+                return input.get(pos + 1) != Some(&0);
+            }
+            Encoding::Utf16Le | Encoding::Utf32Le => (pos, pos + 1..pos + self.unit_size()),
+            Encoding::Utf16Be | Encoding::Utf32Be => match pos.checked_sub(self.unit_size() - 1) {
+                Some(unit) => (unit, unit..pos),
+                None => return false,
+            },
+        };
+        unit % self.unit_size() == offset
+            && input
+                .get(zeros)
+                .is_some_and(|zeros| zeros.iter().all(|&b| b == 0))
+    }
+}
+
+/// Return every code unit alignment of the `encodings`, as (encoding,
+/// offset), in the order of the encodings.
+pub(crate) fn alignments(encodings: &[Encoding]) -> Vec<(Encoding, usize)> {
+    encodings
+        .iter()
+        .flat_map(|&encoding| (0..encoding.unit_size()).map(move |offset| (encoding, offset)))
+        .collect()
+}
+
+/// Return the code unit alignments, out of `alignments`, that the JSON
+/// strings of `input` may be in, judging by the bytes around the brackets,
+/// and also the quotes, if `quotes` is set.
+pub(crate) fn detect(
+    input: &[u8],
+    alignments: &[(Encoding, usize)],
+    quotes: bool,
+) -> Vec<(Encoding, usize)> {
+    let mut found = vec![false; alignments.len()];
+    let candidates: Box<dyn Iterator<Item = usize>> = match quotes {
+        true => Box::new(memchr::memchr3_iter(
+            CHAR_LEFT_CURLY_BRACKET,
+            CHAR_LEFT_SQUARE_BRACKET,
+            CHAR_QUOT_MARK,
+            input,
+        )),
+        false => Box::new(memchr::memchr2_iter(
+            CHAR_LEFT_CURLY_BRACKET,
+            CHAR_LEFT_SQUARE_BRACKET,
+            input,
+        )),
+    };
+    for pos in candidates {
+        for (found, (encoding, offset)) in found.iter_mut().zip(alignments) {
+            *found |= encoding.may_encode(input, pos, *offset);
+        }
+        if found.iter().all(|&found| found) {
+            break;
+        }
+    }
+    alignments
+        .iter()
+        .zip(found)
+        .filter_map(|(&alignment, found)| found.then_some(alignment))
+        .collect()
+}
+
+/// A part of the byte stream that starts at position `base`, transcoded to
+/// UTF-8 from an encoding, starting at `offset`, a chunk at a time.
+struct View<'a> {
+    input: &'a [u8],
+    base: usize,
+    encoding: Encoding,
+    offset: usize,
+    /// The number of code units that are transcoded at a time.
+    chunk_size: usize,
+    /// The index of the first code unit that has not been transcoded yet.
+    unit: usize,
+    /// The length of the text that has been transcoded so far.
+    len: usize,
+    /// The positions of some characters in the transcoded text, and the
+    /// index of their first code unit, in ascending order.
+    checkpoints: Vec<(usize, usize)>,
+}
+
+impl<'a> View<'a> {
+    fn new(input: &'a [u8], base: usize, encoding: Encoding, offset: usize) -> Self {
+        View {
+            input,
+            base,
+            encoding,
+            offset,
+            chunk_size: TRANSCODE_CHUNK_SIZE,
+            unit: 0,
+            len: 0,
+            checkpoints: vec![(0, 0)],
+        }
+    }
+
+    /// Transcode the next chunk of the text, and return it, or `None` if
+    /// the whole text has been transcoded. `buf` holds the transcoded bytes,
+    /// unless the encoding is UTF-8, in which case the chunk is borrowed
+    /// from the byte stream.
+    fn next_chunk<'b>(&mut self, buf: &'b mut Vec<u8>) -> Option<&'b [u8]>
+    where
+        'a: 'b,
+    {
+        let end = self.unit + self.chunk_size;
+        if self.encoding == Encoding::Utf8 {
+            let chunk = &self.input[self.unit.min(self.input.len())..end.min(self.input.len())];
+            self.unit += chunk.len();
+            self.len += chunk.len();
+            return (!chunk.is_empty()).then_some(chunk);
+        }
+
+        buf.clear();
+        let mut utf8 = [0; 4];
+        for (c, units) in self.chars(self.unit) {
+            if self.unit >= end {
+                break;
+            }
+            if self.unit >= self.checkpoints.len() * CHECKPOINT_INTERVAL {
+                self.checkpoints.push((self.len, self.unit));
+            }
+            buf.extend_from_slice(c.encode_utf8(&mut utf8).as_bytes());
+            self.len += c.len_utf8();
+            self.unit += units;
+        }
+        (!buf.is_empty()).then_some(&buf[..])
+    }
+
+    /// Decode the characters from the code unit at index `unit` onwards,
+    /// along with the number of code units that each one takes up. Unpaired
+    /// surrogates, and code units that are not characters, are decoded as
+    /// U+FFFD.
+    fn chars(&self, unit: usize) -> Box<dyn Iterator<Item = (char, usize)> + 'a> {
+        let encoding = self.encoding;
+        let start = self.offset + unit * encoding.unit_size();
+        let bytes = &self.input[start.min(self.input.len())..];
+        match encoding {
+            Encoding::Utf8 => unreachable!(),
+            Encoding::Latin1 => Box::new(bytes.iter().map(|&b| (b as char, 1))),
+            Encoding::Utf16Le | Encoding::Utf16Be => {
+                let units = bytes.chunks_exact(2).map(move |b| match encoding {
+                    Encoding::Utf16Be => u16::from_be_bytes([b[0], b[1]]),
+                    _ => u16::from_le_bytes([b[0], b[1]]),
+                });
+                Box::new(char::decode_utf16(units).map(|c| match c {
+                    Ok(c) => (c, c.len_utf16()),
+                    Err(_) => (REPLACEMENT_CHARACTER, 1),
+                }))
+            }
+            Encoding::Utf32Le | Encoding::Utf32Be => {
+                Box::new(bytes.chunks_exact(4).map(move |b| {
+                    let b = [b[0], b[1], b[2], b[3]];
+                    let unit = match encoding {
+                        Encoding::Utf32Be => u32::from_be_bytes(b),
+                        _ => u32::from_le_bytes(b),
+                    };
+                    (char::from_u32(unit).unwrap_or(REPLACEMENT_CHARACTER), 1)
+                }))
+            }
+        }
+    }
+
+    /// Return the positions of the first and last byte of the character
+    /// that the byte at `pos` of the transcoded text belongs to, within the
+    /// byte stream.
+    fn locate(&self, pos: usize) -> (usize, usize) {
+        if self.encoding == Encoding::Utf8 {
+            return (self.base + pos, self.base + pos);
+        }
+        let unit_size = self.encoding.unit_size();
+        let i = self
+            .checkpoints
+            .partition_point(|&(text_pos, _)| text_pos <= pos)
+            - 1;
+        let (mut text_pos, mut unit) = self.checkpoints[i];
+        for (c, units) in self.chars(unit) {
+            if pos < text_pos + c.len_utf8() {
+                let first = self.base + self.offset + unit * unit_size;
+                return (first, first + units * unit_size - 1);
+            }
+            text_pos += c.len_utf8();
+            unit += units;
+        }
+        // The position right after the end of the transcoded text.
+        let end = self.base + self.offset + unit * unit_size;
+        (end, end)
+    }
+
+    /// Map the positions of a hit in the transcoded text to the byte stream,
+    /// and annotate it with the encoding of the text.
+    fn translate(&self, hit: &Hit) -> io::Result<Hit<'static>> {
+        let mut hit = hit.clone().into_owned()?;
+        hit.start = self.locate(hit.start).0;
+        hit.end = self.locate(hit.end).1;
+        hit.partial_end = self.locate(hit.partial_end).1;
+        hit.parent = hit.parent.map(|parent| self.locate(parent).0);
+        hit.encoding = Some(self.encoding);
+        for candidate in &mut hit.candidates {
+            candidate.end = self.locate(candidate.end).1;
+            for edit in &mut candidate.edits {
+                match edit {
+                    Edit::Drop { start, end } => {
+                        *start = self.locate(*start).0;
+                        *end = self.locate(*end).1;
+                    }
+                    Edit::Insert { at, .. } => *at = self.locate(*at).0,
+                }
+            }
+        }
+        Ok(hit)
+    }
+}
+
+/// A sink that collects the hits of a transcoded text, with their positions
+/// in the byte stream.
+struct Collect<'v, 'a> {
+    view: &'v View<'a>,
+    /// Whether to drop the hits that are not valid UTF-8, because they are
+    /// carved as Latin-1 as well.
+    valid_only: bool,
+    hits: Vec<Hit<'static>>,
+}
+
+impl Sink for Collect<'_, '_> {
+    fn on_hit(&mut self, hit: &Hit) -> io::Result<()> {
+        let hit = self.view.translate(hit)?;
+        if !self.valid_only || std::str::from_utf8(&hit.data).is_ok() {
+            self.hits.push(hit);
+        }
+        Ok(())
+    }
+}
+
+/// A code unit alignment of a part of the byte stream, that is transcoded
+/// and carved a chunk at a time.
+struct Stream<'a> {
+    view: View<'a>,
+    carver: Carver<io::Empty>,
+    /// The transcoded bytes of the current chunk.
+    buf: Vec<u8>,
+    /// Whether to drop the hits that are not valid UTF-8 (see [`Collect`]).
+    valid_only: bool,
+    done: bool,
+}
+
+impl Stream<'_> {
+    /// Carve the next chunk of the text, or finish the carver if there is
+    /// none left, and return the JSON strings that it has stopped, with
+    /// their positions in the byte stream.
+    fn advance(&mut self) -> io::Result<Vec<Hit<'static>>> {
+        let chunk = self.view.next_chunk(&mut self.buf);
+        let mut collect = Collect {
+            view: &self.view,
+            valid_only: self.valid_only,
+            hits: vec![],
+        };
+        match chunk {
+            Some(chunk) => self.carver.feed(chunk, &mut collect)?,
+            None => {
+                self.carver.finish(&mut collect)?;
+                self.done = true;
+            }
+        }
+        Ok(collect.hits)
+    }
+
+    /// Return the position in the byte stream that the JSON strings that
+    /// the carver has yet to report start at or after.
+    fn watermark(&self) -> usize {
+        let jt = &self.carver.jt;
+        let pos = match jt.in_progress() {
+            true => jt.start.min(jt.pos),
+            false => jt.pos,
+        };
+        // Suffixes are recovered from the bytes that the carver remembers.
+        let pos = pos.saturating_sub(self.carver.history_window().unwrap_or(0));
+        self.view.locate(pos).0
+    }
+}
+
+impl<R> Carver<R> {
+    /// Carve a buffer for JSON strings in any of the `encodings`, with
+    /// multiple threads, and pass them to `sink`, in the order of their
+    /// positions.
+    ///
+    /// Only the code unit alignments of the encodings that some opening
+    /// bracket of the buffer may belong to are carved, by up to `threads`
+    /// threads at a time, unless the only encoding is UTF-8 (see
+    /// [`Carver::parse_parallel`]). JSON strings that are
+    /// not in UTF-8 are transcoded to it, so sizes, e.g.,
+    /// [`Carver::min_size`], are measured in transcoded bytes, and page
+    /// boundaries (see [`Carver::page_size`]) are not detected. Their
+    /// positions, though, are the positions of their bytes in the buffer,
+    /// and their encoding is set in [`Hit::encoding`].
+    ///
+    /// A JSON string in one encoding may look like a shorter one in another,
+    /// so JSON strings that lie within a JSON string of another encoding or
+    /// alignment are dropped. If two JSON strings span the same bytes, the
+    /// one whose encoding comes first in `encodings` is kept. The JSON
+    /// strings are passed to `sink` as soon as no other alignment may report
+    /// one that comes before them.
+    pub fn parse_encoded<S: Sink + ?Sized>(
+        &self,
+        input: &[u8],
+        encodings: &[Encoding],
+        threads: usize,
+        sink: &mut S,
+    ) -> io::Result<()> {
+        if encodings == [Encoding::Utf8] {
+            return self.parse_parallel(input, threads, sink);
+        }
+
+        let quotes = self.jt.min_string_len.is_some();
+        let alignments = detect(input, &alignments(encodings), quotes);
+        self.carve_alignments(input, 0, &alignments, Some(threads), |hit| {
+            sink.on_hit(&hit)
+        })
+    }
+
+    /// Carve `input`, a part of the byte stream that starts at position
+    /// `base`, in every one of the `alignments`, and pass the JSON strings
+    /// that should be reported to `on_hit`, in the order of their positions
+    /// (see [`Carver::parse_encoded`]).
+    ///
+    /// The alignments are transcoded and carved a chunk at a time, by up to
+    /// `threads` threads, if set, or else by the current thread, which is
+    /// faster for small parts. After every chunk, their JSON strings are
+    /// merged by position, up to where none of them may start another one.
+    pub(crate) fn carve_alignments<F>(
+        &self,
+        input: &[u8],
+        base: usize,
+        alignments: &[(Encoding, usize)],
+        threads: Option<usize>,
+        on_hit: F,
+    ) -> io::Result<()>
+    where
+        F: FnMut(Hit<'static>) -> io::Result<()>,
+    {
+        self.carve_chunks(
+            input,
+            base,
+            alignments,
+            threads,
+            TRANSCODE_CHUNK_SIZE,
+            on_hit,
+        )
+    }
+
+    fn carve_chunks<F>(
+        &self,
+        input: &[u8],
+        base: usize,
+        alignments: &[(Encoding, usize)],
+        threads: Option<usize>,
+        chunk_size: usize,
+        mut on_hit: F,
+    ) -> io::Result<()>
+    where
+        F: FnMut(Hit<'static>) -> io::Result<()>,
+    {
+        let latin1 = alignments
+            .iter()
+            .any(|(encoding, _)| *encoding == Encoding::Latin1);
+        let mut streams: Vec<Stream> = alignments
+            .iter()
+            .map(|&(encoding, offset)| Stream {
+                view: View {
+                    chunk_size,
+                    ..View::new(input, base, encoding, offset)
+                },
+                carver: self.fork(),
+                buf: vec![],
+                valid_only: latin1 && encoding == Encoding::Utf8,
+                done: false,
+            })
+            .collect();
+        // The JSON strings that the other alignments may still have to be
+        // merged with, in the order of their positions, as (alignment
+        // index, JSON string).
+        let mut pending: Vec<(usize, Hit<'static>)> = vec![];
+        // The furthest end of the reported JSON strings of every alignment.
+        let mut ends: Vec<(usize, usize)> = vec![];
+        while streams.iter().any(|stream| !stream.done) {
+            for (index, batch) in advance(&mut streams, threads)?.into_iter().enumerate() {
+                pending.extend(batch.into_iter().map(|hit| (index, hit)));
+            }
+            pending.sort_by_key(|(index, hit)| (hit.start, Reverse(hit.end), *index));
+            let watermark = streams
+                .iter()
+                .filter(|stream| !stream.done)
+                .map(Stream::watermark)
+                .min()
+                .unwrap_or(usize::MAX);
+            let ready = pending.partition_point(|(_, hit)| hit.start < watermark);
+            for (index, hit) in pending.drain(..ready) {
+                let within = ends.iter().any(|&(i, end)| i != index && hit.end <= end);
+                if within {
+                    continue;
+                }
+                match ends.iter_mut().find(|(i, _)| *i == index) {
+                    Some((_, end)) => *end = hit.end.max(*end),
+                    None => ends.push((index, hit.end)),
+                }
+                on_hit(hit)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Carve the next chunk of every one of the `streams` that is not done,
+/// with up to `threads` threads, and return the JSON strings that each one
+/// has stopped.
+fn advance(streams: &mut [Stream], threads: Option<usize>) -> io::Result<Vec<Vec<Hit<'static>>>> {
+    let advance = |stream: &mut Stream| match stream.done {
+        true => Ok(vec![]),
+        false => stream.advance(),
+    };
+    let threads = match threads {
+        Some(threads) if threads > 1 && streams.len() > 1 => threads,
+        _ => return streams.iter_mut().map(advance).collect(),
+    };
+    let per_thread = streams.len().div_ceil(threads);
+    thread::scope(|scope| {
+        let handles: Vec<_> = streams
+            .chunks_mut(per_thread)
+            .map(|batch| scope.spawn(move || batch.iter_mut().map(advance).collect::<Vec<_>>()))
+            .collect();
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().unwrap())
+            .collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::create_carver;
+    use crate::{RepairMode, Status};
+
+    #[derive(Default)]
+    struct Hits(Vec<Hit<'static>>);
+
+    impl Sink for Hits {
+        fn on_hit(&mut self, hit: &Hit) -> io::Result<()> {
+            self.0.push(hit.clone().into_owned()?);
+            Ok(())
+        }
+    }
+
+    fn encode(text: &str, encoding: Encoding) -> Vec<u8> {
+        match encoding {
+            Encoding::Utf8 => text.as_bytes().to_vec(),
+            Encoding::Latin1 => text.chars().map(|c| u8::try_from(c).unwrap()).collect(),
+            Encoding::Utf16Le => text.encode_utf16().flat_map(u16::to_le_bytes).collect(),
+            Encoding::Utf16Be => text.encode_utf16().flat_map(u16::to_be_bytes).collect(),
+            Encoding::Utf32Le => text
+                .chars()
+                .flat_map(|c| u32::from(c).to_le_bytes())
+                .collect(),
+            Encoding::Utf32Be => text
+                .chars()
+                .flat_map(|c| u32::from(c).to_be_bytes())
+                .collect(),
+        }
+    }
+
+    fn carve(input: &[u8], encodings: &[Encoding]) -> Vec<(Status, usize, usize, String)> {
+        let mut carver = Carver::new(input, None, None);
+        carver.fix_incomplete = true;
+        carver.repair_mode = RepairMode::Values;
+        let mut hits = Hits::default();
+        carver
+            .parse_encoded(input, encodings, 1, &mut hits)
+            .unwrap();
+        hits.0
+            .into_iter()
+            .map(|hit| {
+                let data = hit.repaired.unwrap_or(hit.data.into_owned());
+                (
+                    hit.status,
+                    hit.start,
+                    hit.end,
+                    String::from_utf8(data).unwrap(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_encoded() {
+        let json = r#"{"msg": "καλημέρα 😀", "n": [1, 2]}"#;
+        let le = encode(json, Encoding::Utf16Le);
+        let be = encode(json, Encoding::Utf16Be);
+        let len = le.len();
+
+        // UTF-16LE at an odd position.
+        let input = [&b"\x01\x02\x03"[..], &le, b"\xff"].concat();
+        let expected = vec![(Status::Completed, 3, len + 2, json.to_string())];
+        assert_eq!(carve(&input, &[Encoding::Utf16Le]), expected);
+        assert_eq!(carve(&input, Encoding::ALL), expected);
+        // Shifted by a byte, UTF-16LE looks like UTF-16BE. The array within
+        // it is reported only if UTF-16LE is not carved.
+        let array = (Status::Completed, len - 12, len - 1, "[1, 2]".to_string());
+        assert_eq!(carve(&input, &[Encoding::Utf16Be]), vec![array]);
+
+        // UTF-16BE at an even position, followed by UTF-8 and an incomplete
+        // UTF-16BE string.
+        let tail = encode(r#"["a", "b"#, Encoding::Utf16Be);
+        let input = [&b"\x00\x00"[..], &be, b"[1, 2]", &tail].concat();
+        assert_eq!(
+            carve(&input, Encoding::ALL),
+            vec![
+                (Status::Completed, 2, len + 1, json.to_string()),
+                (Status::Completed, len + 2, len + 7, "[1, 2]".to_string()),
+                (
+                    Status::Exhausted,
+                    len + 8,
+                    input.len() - 1,
+                    r#"["a", "b"]"#.to_string()
+                ),
+            ]
+        );
+
+        // Unpaired surrogates are replaced with U+FFFD.
+        let input = [
+            &encode(r#"[""#, Encoding::Utf16Le)[..],
+            b"\x00\xd8",
+            &encode(r#""]"#, Encoding::Utf16Le),
+        ]
+        .concat();
+        assert_eq!(
+            carve(&input, &[Encoding::Utf16Le]),
+            vec![(Status::Completed, 0, 9, "[\"\u{fffd}\"]".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_mixed_encodings() {
+        let parts = [
+            (Encoding::Utf16Le, r#"{"msg": "καλημέρα"}"#),
+            (Encoding::Utf32Be, r#"{"msg": "😀"}"#),
+            (Encoding::Utf8, "[1, 2]"),
+            (Encoding::Latin1, r#"{"a": "café"}"#),
+            (Encoding::Utf32Le, r#"["€"]"#),
+            (Encoding::Utf8, r#"{"b": "é"}"#),
+        ];
+        let mut input = vec![];
+        let mut expected = vec![];
+        for (encoding, json) in parts {
+            input.extend_from_slice(b"\x01\x02\x03");
+            let bytes = encode(json, encoding);
+            let start = input.len();
+            input.extend_from_slice(&bytes);
+            expected.push((start, input.len() - 1, json.to_string(), Some(encoding)));
+        }
+
+        let carver = Carver::new(&input[..], None, None);
+        let mut hits = Hits::default();
+        carver
+            .parse_encoded(&input, Encoding::ALL, 2, &mut hits)
+            .unwrap();
+        let hits: Vec<_> = hits
+            .0
+            .into_iter()
+            .map(|hit| {
+                let data = String::from_utf8(hit.data.into_owned()).unwrap();
+                (hit.start, hit.end, data, hit.encoding)
+            })
+            .collect();
+        assert_eq!(hits, expected);
+    }
+
+    #[test]
+    fn test_carve_chunks() {
+        let parts = [
+            (Encoding::Utf16Le, r#"{"a": [1, {"b": "é"}]}, "c"}]"#),
+            (Encoding::Utf8, r#"[1, {"key": "val"#),
+            (Encoding::Utf8, r#""body": [1], "x": 2}]"#),
+            (Encoding::Utf32Be, r#"["😀", [2]], 3]"#),
+            (Encoding::Utf16Be, r#"{"msg": "καλημέρα", "n": [1, 2]"#),
+            (Encoding::Latin1, r#"{"a": "café"}]"#),
+        ];
+        let mut input = vec![];
+        for (encoding, json) in parts {
+            input.extend_from_slice(b"\x01\x02\x03");
+            input.extend_from_slice(&encode(json, encoding));
+        }
+        let configs: [fn(&mut Carver<&[u8]>); 4] = [
+            |_| {},
+            |carver| carver.max_size(Some(7)),
+            |carver| {
+                carver.salvage(true);
+                carver.recover_suffixes(Some(64));
+            },
+            |carver| {
+                carver.exhaustive(true);
+                carver.strings(Some(2));
+            },
+        ];
+        let alignments = alignments(Encoding::ALL);
+        for setup in configs {
+            let mut carver = create_carver(&input);
+            carver.fix_incomplete = true;
+            setup(&mut carver);
+            let carve = |threads, chunk_size| {
+                let mut hits = vec![];
+                carver
+                    .carve_chunks(&input, 0, &alignments, threads, chunk_size, |hit| {
+                        hits.push(hit);
+                        Ok(())
+                    })
+                    .unwrap();
+                hits
+            };
+            let expected = carve(None, input.len());
+            assert!(!expected.is_empty());
+
+            for chunk_size in 1..=input.len() / 4 {
+                for threads in [None, Some(3)] {
+                    assert_eq!(
+                        carve(threads, chunk_size),
+                        expected,
+                        "chunk size {chunk_size}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_detect() {
+        let alignments = alignments(Encoding::ALL);
+        let detect = |input: &[u8], quotes| detect(input, &alignments, quotes);
+
+        assert_eq!(detect(b"", false), vec![]);
+        assert_eq!(
+            detect(b"x{1", false),
+            vec![(Encoding::Utf8, 0), (Encoding::Latin1, 0)]
+        );
+        assert_eq!(detect(b"x\"a\"", false), vec![]);
+        assert_eq!(
+            detect(b"x\"a\"", true),
+            vec![(Encoding::Utf8, 0), (Encoding::Latin1, 0)]
+        );
+        assert_eq!(detect(b"x[\x00\"\x00", false), vec![(Encoding::Utf16Le, 1)]);
+        assert_eq!(
+            detect(b"x\x00[\x00\"", false),
+            vec![(Encoding::Utf16Le, 0), (Encoding::Utf16Be, 1)]
+        );
+        assert_eq!(
+            detect(b"xx\x00\x00\x00{\x00\x00\x00", false),
+            vec![
+                (Encoding::Utf16Le, 1),
+                (Encoding::Utf16Be, 0),
+                (Encoding::Utf32Le, 1),
+                (Encoding::Utf32Be, 2),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_locate() {
+        let text: String = (0..3 * CHECKPOINT_INTERVAL)
+            .map(|i| ['a', 'é', '😀', '€'][i % 4])
+            .collect();
+        let mut input = encode(&text, Encoding::Utf16Be);
+        // An unpaired surrogate, and an odd byte at the end.
+        input.extend_from_slice(b"\xdc\x00\x00");
+        let mut view = View::new(&input, 0, Encoding::Utf16Be, 0);
+        // The chunks that would end in the middle of a surrogate pair take
+        // the whole pair.
+        view.chunk_size = CHECKPOINT_INTERVAL + 1;
+        let mut buf = vec![];
+        let mut transcoded = vec![];
+        while let Some(chunk) = view.next_chunk(&mut buf) {
+            transcoded.extend_from_slice(chunk);
+        }
+        assert_eq!(
+            transcoded,
+            text.as_bytes()
+                .iter()
+                .chain("\u{fffd}".as_bytes())
+                .copied()
+                .collect::<Vec<_>>()
+        );
+        assert!(view.checkpoints.len() > 1);
+
+        let mut pos = 0;
+        let mut first = 0;
+        // Decoding from a checkpoint takes a while, so only some of the
+        // characters are checked.
+        for (i, c) in text.chars().chain([REPLACEMENT_CHARACTER]).enumerate() {
+            let last = first + 2 * c.len_utf16() - 1;
+            for _ in 0..c.len_utf8() {
+                if i % 13 == 0 || i >= 3 * CHECKPOINT_INTERVAL - 2 {
+                    assert_eq!(view.locate(pos), (first, last));
+                }
+                pos += 1;
+            }
+            first = last + 1;
+        }
+        assert_eq!(pos, transcoded.len());
+        assert_eq!(view.locate(pos), (first, first));
+    }
+}
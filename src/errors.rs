@@ -6,4 +6,6 @@ use thiserror::Error;
 pub enum Err {
     #[error("I/O operation failed")]
     StreamError(#[from] io::Error),
+    #[error("Malformed HPROF heap dump at position {pos}: {reason}")]
+    HprofError { pos: usize, reason: &'static str },
 }
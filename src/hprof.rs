@@ -0,0 +1,810 @@
+//! Carve the strings of a Java heap dump.
+//!
+//! A heap dump in the HPROF format is a sequence of records, and the objects
+//! of the heap are sub-records of its heap dump records. The characters of a
+//! `java.lang.String` are not stored in the object itself, but in an array
+//! that it refers to: a `char[]` up to Java 8, or, since Java 9, a `byte[]`
+//! with a `coder` field, which tells whether the characters are in Latin-1
+//! or in UTF-16.
+//!
+//! Carving the dump as raw bytes misses JSON strings in UTF-16, and does not
+//! tell which object a JSON string belongs to. Instead, we walk the records
+//! twice: once to find the classes, and once to pair every string with its
+//! array. Then, every string, and every `byte[]` or `char[]` that does not
+//! belong to a string, is carved on its own, in its own encoding. The other
+//! primitive arrays cannot hold text, so they are skipped.
+//!
+//! See the format in the `heapDumper.cpp` file of the OpenJDK sources.
+
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+
+use crate::encoding::{alignments, detect};
+use crate::{Carver, Encoding, Sink, errors};
+
+/// The magic string that every HPROF heap dump starts with.
+const MAGIC: &[u8] = b"JAVA PROFILE ";
+
+// Record tags.
+const TAG_UTF8: u8 = 0x01;
+const TAG_LOAD_CLASS: u8 = 0x02;
+const TAG_HEAP_DUMP: u8 = 0x0C;
+const TAG_HEAP_DUMP_SEGMENT: u8 = 0x1C;
+
+// Heap dump sub-record tags.
+const SUB_ROOT_UNKNOWN: u8 = 0xFF;
+const SUB_ROOT_JNI_GLOBAL: u8 = 0x01;
+const SUB_ROOT_JNI_LOCAL: u8 = 0x02;
+const SUB_ROOT_JAVA_FRAME: u8 = 0x03;
+const SUB_ROOT_NATIVE_STACK: u8 = 0x04;
+const SUB_ROOT_STICKY_CLASS: u8 = 0x05;
+const SUB_ROOT_THREAD_BLOCK: u8 = 0x06;
+const SUB_ROOT_MONITOR_USED: u8 = 0x07;
+const SUB_ROOT_THREAD_OBJECT: u8 = 0x08;
+const SUB_CLASS_DUMP: u8 = 0x20;
+const SUB_INSTANCE_DUMP: u8 = 0x21;
+const SUB_OBJ_ARRAY_DUMP: u8 = 0x22;
+const SUB_PRIM_ARRAY_DUMP: u8 = 0x23;
+// Android extensions.
+const SUB_ANDROID_ROOTS: [u8; 6] = [0x89, 0x8A, 0x8B, 0x8C, 0x8D, 0x90];
+const SUB_ANDROID_ROOT_JNI_MONITOR: u8 = 0x8E;
+const SUB_ANDROID_PRIM_ARRAY_NODATA: u8 = 0xC3;
+const SUB_ANDROID_HEAP_DUMP_INFO: u8 = 0xFE;
+
+// Basic types.
+const TYPE_OBJECT: u8 = 2;
+const TYPE_CHAR: u8 = 5;
+const TYPE_BYTE: u8 = 8;
+
+/// The `coder` of a `java.lang.String` whose characters are in UTF-16.
+const CODER_UTF16: u8 = 1;
+
+/// An object of a Java heap dump (see [`crate::Hit::object`]).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HeapObject {
+    /// The ID of the object in the heap dump.
+    pub id: u64,
+    /// The class of the object, e.g., `java.lang.String` or `byte[]`.
+    pub class: String,
+}
+
+/// A record, or a heap dump sub-record, that matters for carving.
+enum Record<'a> {
+    /// A name, e.g., of a class or a field.
+    Name { id: u64, name: &'a [u8] },
+    /// A class, and the ID of its name.
+    LoadClass { class: u64, name: u64 },
+    /// The instance fields of a class, as (name ID, type).
+    ClassDump { class: u64, fields: Vec<(u64, u8)> },
+    /// An object, and the values of its instance fields.
+    Instance {
+        id: u64,
+        class: u64,
+        values: &'a [u8],
+    },
+    /// An array of a primitive type, and the position of its elements.
+    PrimitiveArray {
+        id: u64,
+        elem: u8,
+        data: Range<usize>,
+    },
+}
+
+/// Return the size of a value of a basic type, in a heap dump whose IDs are
+/// `id_size` bytes long.
+fn size_of(ty: u8, id_size: usize) -> Option<usize> {
+    match ty {
+        TYPE_OBJECT => Some(id_size),
+        // boolean, byte
+        4 | 8 => Some(1),
+        // char, short
+        5 | 9 => Some(2),
+        // float, int
+        6 | 10 => Some(4),
+        // double, long
+        7 | 11 => Some(8),
+        _ => None,
+    }
+}
+
+/// A reader for the big-endian values of a heap dump.
+struct Cursor<'a> {
+    input: &'a [u8],
+    pos: usize,
+    id_size: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn error(&self, reason: &'static str) -> errors::Err {
+        errors::Err::HprofError {
+            pos: self.pos,
+            reason,
+        }
+    }
+
+    fn bytes(&mut self, n: usize) -> Result<&'a [u8], errors::Err> {
+        let end = self
+            .pos
+            .checked_add(n)
+            .filter(|&end| end <= self.input.len());
+        let bytes = &self.input[self.pos..end.ok_or(self.error("truncated record"))?];
+        self.pos += n;
+        Ok(bytes)
+    }
+
+    fn uint(&mut self, n: usize) -> Result<u64, errors::Err> {
+        Ok(self
+            .bytes(n)?
+            .iter()
+            .fold(0, |value, &b| (value << 8) | u64::from(b)))
+    }
+
+    fn u1(&mut self) -> Result<u8, errors::Err> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u2(&mut self) -> Result<usize, errors::Err> {
+        Ok(self.uint(2)? as usize)
+    }
+
+    fn u4(&mut self) -> Result<usize, errors::Err> {
+        Ok(self.uint(4)? as usize)
+    }
+
+    fn id(&mut self) -> Result<u64, errors::Err> {
+        self.uint(self.id_size)
+    }
+
+    /// The size of a value of a basic type.
+    fn size_of(&self, ty: u8) -> Result<usize, errors::Err> {
+        size_of(ty, self.id_size).ok_or(self.error("unknown basic type"))
+    }
+
+    /// Skip a value of a basic type.
+    fn skip_value(&mut self) -> Result<(), errors::Err> {
+        let ty = self.u1()?;
+        self.bytes(self.size_of(ty)?)?;
+        Ok(())
+    }
+
+    /// Read the heap dump sub-record that starts at the current position,
+    /// and return it, if it matters for carving.
+    fn sub_record(&mut self) -> Result<Option<Record<'a>>, errors::Err> {
+        let id_size = self.id_size;
+        let tag = self.u1()?;
+        let skip = match tag {
+            SUB_ROOT_UNKNOWN | SUB_ROOT_STICKY_CLASS | SUB_ROOT_MONITOR_USED => id_size,
+            SUB_ROOT_JNI_GLOBAL => 2 * id_size,
+            SUB_ROOT_JNI_LOCAL | SUB_ROOT_JAVA_FRAME | SUB_ROOT_THREAD_OBJECT => id_size + 8,
+            SUB_ROOT_NATIVE_STACK | SUB_ROOT_THREAD_BLOCK => id_size + 4,
+            _ if SUB_ANDROID_ROOTS.contains(&tag) => id_size,
+            SUB_ANDROID_ROOT_JNI_MONITOR => id_size + 8,
+            SUB_ANDROID_PRIM_ARRAY_NODATA => id_size + 9,
+            SUB_ANDROID_HEAP_DUMP_INFO => 4 + id_size,
+            SUB_CLASS_DUMP => {
+                let class = self.id()?;
+                // The stack trace serial, the superclass, the class loader,
+                // the signers, the protection domain and two reserved IDs.
+                self.bytes(4 + 6 * id_size)?;
+                // The instance size.
+                self.u4()?;
+                for _ in 0..self.u2()? {
+                    // The index of the constant pool entry.
+                    self.u2()?;
+                    self.skip_value()?;
+                }
+                for _ in 0..self.u2()? {
+                    // The name of the static field.
+                    self.id()?;
+                    self.skip_value()?;
+                }
+                let mut fields = vec![];
+                for _ in 0..self.u2()? {
+                    fields.push((self.id()?, self.u1()?));
+                }
+                return Ok(Some(Record::ClassDump { class, fields }));
+            }
+            SUB_INSTANCE_DUMP => {
+                let id = self.id()?;
+                self.u4()?;
+                let class = self.id()?;
+                let len = self.u4()?;
+                let values = self.bytes(len)?;
+                return Ok(Some(Record::Instance { id, class, values }));
+            }
+            SUB_OBJ_ARRAY_DUMP => {
+                self.bytes(id_size + 4)?;
+                let count = self.u4()?;
+                self.id()?;
+                count.saturating_mul(id_size)
+            }
+            SUB_PRIM_ARRAY_DUMP => {
+                let id = self.id()?;
+                self.u4()?;
+                let count = self.u4()?;
+                let elem = self.u1()?;
+                let start = self.pos;
+                self.bytes(count.saturating_mul(self.size_of(elem)?))?;
+                let data = start..self.pos;
+                return Ok(Some(Record::PrimitiveArray { id, elem, data }));
+            }
+            _ => {
+                self.pos -= 1;
+                return Err(self.error("unknown heap dump sub-record"));
+            }
+        };
+        self.bytes(skip)?;
+        Ok(None)
+    }
+}
+
+impl<'a> Cursor<'a> {
+    /// Read the body of a record with the tag `tag`, which ends where the
+    /// input of the cursor ends, and pass the records that matter for carving
+    /// to `visit`.
+    fn record(&mut self, tag: u8, visit: &mut impl FnMut(Record<'a>)) -> Result<(), errors::Err> {
+        match tag {
+            TAG_UTF8 => {
+                let id = self.id()?;
+                let name = &self.input[self.pos..];
+                visit(Record::Name { id, name });
+            }
+            TAG_LOAD_CLASS => {
+                // The class serial.
+                self.u4()?;
+                let class = self.id()?;
+                // The stack trace serial.
+                self.u4()?;
+                let name = self.id()?;
+                visit(Record::LoadClass { class, name });
+            }
+            TAG_HEAP_DUMP | TAG_HEAP_DUMP_SEGMENT => {
+                while self.pos < self.input.len() {
+                    if let Some(record) = self.sub_record()? {
+                        visit(record);
+                    }
+                }
+            }
+            _ => (),
+        }
+        Ok(())
+    }
+}
+
+/// Walk the records of a heap dump, pass the ones that matter for carving to
+/// `visit`, and return the size of its IDs.
+///
+/// Heap dumps are often truncated, so the last record may end past the end of
+/// the dump. Its sub-records are read up to the last complete one. A record
+/// that is malformed otherwise is read up to the error, which is passed to
+/// `malformed`, and the walk goes on with the next record.
+fn walk<'a>(
+    input: &'a [u8],
+    mut visit: impl FnMut(Record<'a>),
+    mut malformed: impl FnMut(errors::Err),
+) -> Result<usize, errors::Err> {
+    let mut cursor = Cursor {
+        input,
+        pos: 0,
+        id_size: 0,
+    };
+    if !input.starts_with(MAGIC) {
+        return Err(cursor.error("not an HPROF heap dump"));
+    }
+    let version_len = memchr::memchr(0, input).ok_or(cursor.error("truncated header"))?;
+    cursor.pos = version_len + 1;
+    cursor.id_size = cursor.u4()?;
+    if !matches!(cursor.id_size, 4 | 8) {
+        return Err(cursor.error("unsupported identifier size"));
+    }
+    // The timestamp of the dump.
+    cursor.bytes(8)?;
+
+    while cursor.pos < input.len() {
+        let tag = cursor.u1()?;
+        // The time offset of the record.
+        cursor.u4()?;
+        let len = cursor.u4()?;
+        let end = input.len().min(cursor.pos + len);
+        let truncated = end < cursor.pos + len;
+        let mut body = Cursor {
+            input: &input[..end],
+            pos: cursor.pos,
+            id_size: cursor.id_size,
+        };
+        cursor.pos = end;
+        match body.record(tag, &mut visit) {
+            Ok(()) => (),
+            Err(_) if truncated => (),
+            Err(e) => malformed(e),
+        }
+    }
+    Ok(cursor.id_size)
+}
+
+/// The position and the reason of the error of a malformed record.
+type Malformed = (usize, &'static str);
+
+/// The part of a heap dump that is carved on its own.
+struct Part {
+    object: HeapObject,
+    data: Range<usize>,
+    /// The alignments of the encodings that the part is carved in, or none,
+    /// if they have to be detected.
+    alignments: Vec<(Encoding, usize)>,
+}
+
+/// The layout of the instance fields of `java.lang.String`.
+#[derive(Clone, Copy, Default)]
+struct StringFields {
+    /// The position of the `value` field within the field values.
+    value: Option<usize>,
+    /// The position of the `coder` field within the field values, if any.
+    coder: Option<usize>,
+}
+
+/// Return the byte order of the UTF-16 characters of the strings whose
+/// `byte[]` arrays are `values`, which is the byte order of the machine that
+/// the heap was dumped on. Most characters of text, and all the brackets and
+/// quotes of JSON, have a zero upper byte, so the order that puts more zero
+/// bytes in the upper half of the code units wins.
+fn utf16_byte_order<'a>(values: impl Iterator<Item = &'a [u8]>) -> Encoding {
+    let (mut le, mut be) = (0, 0);
+    for unit in values.flat_map(|data| data.chunks_exact(2)) {
+        match unit {
+            [_, 0] => le += 1,
+            [0, _] => be += 1,
+            _ => (),
+        }
+    }
+    match be > le {
+        true => Encoding::Utf16Be,
+        false => Encoding::Utf16Le,
+    }
+}
+
+/// Find the strings and the `byte[]` and `char[]` arrays of a heap dump, in
+/// the order of the positions of their characters, along with the errors of
+/// the records that are malformed (see [`walk`]).
+fn parts(input: &[u8]) -> Result<(Vec<Part>, Vec<Malformed>), errors::Err> {
+    let mut names = HashMap::new();
+    let mut class_names = HashMap::new();
+    let mut class_fields = HashMap::new();
+    let id_size = walk(
+        input,
+        |record| match record {
+            Record::Name { id, name } => {
+                names.insert(id, name);
+            }
+            Record::LoadClass { class, name } => {
+                class_names.insert(class, name);
+            }
+            Record::ClassDump { class, fields } => {
+                class_fields.insert(class, fields);
+            }
+            _ => (),
+        },
+        |_| (),
+    )?;
+
+    // A dump may hold more than one `java.lang.String` class, one per class
+    // loader.
+    let mut strings = HashMap::new();
+    for (&class, name) in &class_names {
+        if names.get(name) != Some(&&b"java/lang/String"[..]) {
+            continue;
+        }
+        let mut layout = StringFields::default();
+        let mut pos = 0;
+        for &(name, ty) in class_fields.get(&class).into_iter().flatten() {
+            match (names.get(&name).copied(), ty) {
+                (Some(b"value"), TYPE_OBJECT) => layout.value = Some(pos),
+                (Some(b"coder"), TYPE_BYTE) => layout.coder = Some(pos),
+                _ => (),
+            }
+            // The fields after one of an unknown type cannot be found.
+            let Some(size) = size_of(ty, id_size) else {
+                break;
+            };
+            pos += size;
+        }
+        strings.insert(class, layout);
+    }
+
+    // Every string, as (ID, array ID, coder), and every array, as (ID,
+    // element type, position of its elements).
+    let mut values = vec![];
+    let mut arrays = vec![];
+    let mut malformed = vec![];
+    walk(
+        input,
+        |record| match record {
+            Record::Instance {
+                id,
+                class,
+                values: fields,
+            } => {
+                let Some(layout) = strings.get(&class) else {
+                    return;
+                };
+                let field = |pos: Option<usize>, len| fields.get(pos?..pos? + len);
+                if let Some(value) = field(layout.value, id_size) {
+                    let value = value.iter().fold(0, |id, &b| (id << 8) | u64::from(b));
+                    let coder = field(layout.coder, 1).map(|coder| coder[0]);
+                    values.push((id, value, coder));
+                }
+            }
+            Record::PrimitiveArray { id, elem, data } if matches!(elem, TYPE_BYTE | TYPE_CHAR) => {
+                arrays.push((id, elem, data));
+            }
+            _ => (),
+        },
+        |error| {
+            if let errors::Err::HprofError { pos, reason } = error {
+                malformed.push((pos, reason));
+            }
+        },
+    )?;
+
+    let array_pos: HashMap<u64, usize> = arrays
+        .iter()
+        .enumerate()
+        .map(|(i, (id, _, _))| (*id, i))
+        .collect();
+    // Since Java 9, strings are `byte[]`, whose UTF-16 characters are in the
+    // byte order of the machine.
+    let utf16 = utf16_byte_order(values.iter().filter_map(|&(_, value, coder)| {
+        let &i = array_pos.get(&value)?;
+        let (_, elem, data) = &arrays[i];
+        (*elem == TYPE_BYTE && coder == Some(CODER_UTF16)).then(|| &input[data.clone()])
+    }));
+    let mut owned = HashSet::new();
+    let mut parts = vec![];
+    for (id, value, coder) in values {
+        let Some(&i) = array_pos.get(&value) else {
+            continue;
+        };
+        let (_, elem, data) = &arrays[i];
+        // Up to Java 8, strings are `char[]`.
+        let encoding = match (elem, coder) {
+            (&TYPE_CHAR, _) => Encoding::Utf16Be,
+            (_, Some(CODER_UTF16)) => utf16,
+            _ => Encoding::Latin1,
+        };
+        owned.insert(value);
+        parts.push(Part {
+            object: HeapObject {
+                id,
+                class: "java.lang.String".to_string(),
+            },
+            data: data.clone(),
+            alignments: vec![(encoding, 0)],
+        });
+    }
+    for (id, elem, data) in arrays {
+        if owned.contains(&id) {
+            continue;
+        }
+        let (class, alignments) = match elem {
+            // Arrays of characters are in UTF-16, like all Java
+            // characters, and big-endian, like all values of the dump.
+            TYPE_CHAR => ("char[]", vec![(Encoding::Utf16Be, 0)]),
+            _ => ("byte[]", vec![]),
+        };
+        parts.push(Part {
+            object: HeapObject {
+                id,
+                class: class.to_string(),
+            },
+            data,
+            alignments,
+        });
+    }
+    parts.sort_by_key(|part| part.data.start);
+    Ok((parts, malformed))
+}
+
+impl<R> Carver<R> {
+    /// Carve a Java heap dump in the HPROF format, and pass the JSON strings
+    /// that it holds to `sink`.
+    ///
+    /// Only the characters of the strings of the heap, and the `byte[]` and
+    /// `char[]` arrays that do not belong to a string, are carved, each one
+    /// on its own, in its own encoding. The characters of `byte[]` arrays
+    /// are detected, like the ones of [`Carver::parse_encoded`].
+    ///
+    /// The positions of the JSON strings are the positions of their bytes in
+    /// the heap dump, and the object that they were found in is set in
+    /// [`crate::Hit::object`]. The records that are malformed are passed to
+    /// [`Sink::on_malformed`], in the order of their positions, and skipped.
+    pub fn parse_hprof<S: Sink + ?Sized>(
+        &self,
+        input: &[u8],
+        sink: &mut S,
+    ) -> Result<(), errors::Err> {
+        let quotes = self.jt.min_string_len.is_some();
+        let (parts, malformed) = parts(input)?;
+        let mut malformed = malformed.into_iter().peekable();
+        for part in parts {
+            while let Some((pos, reason)) = malformed.next_if(|(pos, _)| *pos < part.data.start) {
+                sink.on_malformed(pos, reason)?;
+            }
+            let data = &input[part.data.clone()];
+            let alignments = match part.alignments.is_empty() {
+                true => detect(data, &alignments(Encoding::ALL), quotes),
+                false => part.alignments,
+            };
+            self.carve_alignments(data, part.data.start, &alignments, None, |mut hit| {
+                hit.object = Some(part.object.clone());
+                sink.on_hit(&hit)
+            })?;
+        }
+        for (pos, reason) in malformed {
+            sink.on_malformed(pos, reason)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use super::*;
+    use crate::Hit;
+
+    #[derive(Default)]
+    struct Hits(Vec<Hit<'static>>);
+
+    impl Sink for Hits {
+        fn on_hit(&mut self, hit: &Hit) -> io::Result<()> {
+            self.0.push(hit.clone().into_owned()?);
+            Ok(())
+        }
+    }
+
+    /// A heap dump with 8-byte IDs.
+    struct Dump {
+        buf: Vec<u8>,
+        heap: Vec<u8>,
+    }
+
+    impl Dump {
+        fn new() -> Self {
+            let mut buf = b"JAVA PROFILE 1.0.2\0".to_vec();
+            buf.extend_from_slice(&8u32.to_be_bytes());
+            buf.extend_from_slice(&0u64.to_be_bytes());
+            Dump { buf, heap: vec![] }
+        }
+
+        fn record(&mut self, tag: u8, body: &[u8]) {
+            self.buf.push(tag);
+            self.buf.extend_from_slice(&0u32.to_be_bytes());
+            self.buf
+                .extend_from_slice(&(body.len() as u32).to_be_bytes());
+            self.buf.extend_from_slice(body);
+        }
+
+        fn name(&mut self, id: u64, name: &str) {
+            self.record(TAG_UTF8, &[&id.to_be_bytes()[..], name.as_bytes()].concat());
+        }
+
+        fn load_class(&mut self, class: u64, name: u64) {
+            let body = [
+                &1u32.to_be_bytes()[..],
+                &class.to_be_bytes(),
+                &0u32.to_be_bytes(),
+                &name.to_be_bytes(),
+            ];
+            self.record(TAG_LOAD_CLASS, &body.concat());
+        }
+
+        fn sub_record(&mut self, tag: u8, body: &[&[u8]]) {
+            self.heap.push(tag);
+            self.heap.extend_from_slice(&body.concat());
+        }
+
+        /// Add a primitive array, and return the position of its elements in
+        /// the dump, once the heap has been flushed.
+        fn array(&mut self, id: u64, elem: u8, data: &[u8]) -> usize {
+            let count = data.len() / size_of(elem, 8).unwrap();
+            let header = [
+                &id.to_be_bytes()[..],
+                &[0; 4],
+                &(count as u32).to_be_bytes(),
+                &[elem],
+            ];
+            self.sub_record(SUB_PRIM_ARRAY_DUMP, &[&header.concat(), data]);
+            // The tag, time and length of the heap dump segment come first.
+            self.buf.len() + 9 + self.heap.len() - data.len()
+        }
+
+        fn flush_heap(&mut self) {
+            let heap = std::mem::take(&mut self.heap);
+            self.record(TAG_HEAP_DUMP_SEGMENT, &heap);
+        }
+    }
+
+    fn utf16le(text: &str) -> Vec<u8> {
+        text.encode_utf16().flat_map(u16::to_le_bytes).collect()
+    }
+
+    fn utf16be(text: &str) -> Vec<u8> {
+        text.encode_utf16().flat_map(u16::to_be_bytes).collect()
+    }
+
+    /// The position, data, encoding, object ID and class of a hit.
+    type Carved = (usize, usize, String, Encoding, u64, String);
+
+    fn carve(input: &[u8]) -> Result<Vec<Carved>, errors::Err> {
+        let carver = Carver::new(input, None, None);
+        let mut hits = Hits::default();
+        carver.parse_hprof(input, &mut hits)?;
+        Ok(hits
+            .0
+            .into_iter()
+            .map(|hit| {
+                let object = hit.object.unwrap();
+                let data = String::from_utf8(hit.data.into_owned()).unwrap();
+                (
+                    hit.start,
+                    hit.end,
+                    data,
+                    hit.encoding.unwrap(),
+                    object.id,
+                    object.class,
+                )
+            })
+            .collect())
+    }
+
+    #[test]
+    fn test_parse_hprof() {
+        let mut dump = Dump::new();
+        dump.name(1, "java/lang/String");
+        dump.name(2, "value");
+        dump.name(3, "hash");
+        dump.name(4, "coder");
+        dump.load_class(100, 1);
+
+        dump.sub_record(SUB_ROOT_STICKY_CLASS, &[&100u64.to_be_bytes()]);
+        let fields: [&[u8]; 3] = [
+            &[&2u64.to_be_bytes()[..], &[TYPE_OBJECT]].concat(),
+            &[&3u64.to_be_bytes()[..], &[10]].concat(),
+            &[&4u64.to_be_bytes()[..], &[TYPE_BYTE]].concat(),
+        ];
+        let class_dump = [
+            &100u64.to_be_bytes()[..],
+            &[0; 4 + 6 * 8 + 4],
+            // A constant and a static field.
+            &1u16.to_be_bytes(),
+            &[0, 0, 10, 0, 0, 0, 7],
+            &1u16.to_be_bytes(),
+            &[&9u64.to_be_bytes()[..], &[TYPE_BYTE, 1]].concat(),
+            &3u16.to_be_bytes(),
+            &fields.concat(),
+        ];
+        dump.sub_record(SUB_CLASS_DUMP, &class_dump);
+        // The strings come before their arrays, and the Latin-1 one comes
+        // after the UTF-16 one.
+        for (id, value, coder) in [(200u64, 300u64, 0), (201, 301, CODER_UTF16)] {
+            let values = [&value.to_be_bytes()[..], &[0; 4], &[coder]].concat();
+            let header = [&id.to_be_bytes()[..], &[0; 4], &100u64.to_be_bytes()];
+            let len = (values.len() as u32).to_be_bytes();
+            dump.sub_record(SUB_INSTANCE_DUMP, &[&header.concat(), &len, &values]);
+        }
+        let obj_array = [
+            &400u64.to_be_bytes()[..],
+            &[0; 4],
+            &1u32.to_be_bytes(),
+            &[7; 16],
+        ];
+        dump.sub_record(SUB_OBJ_ARRAY_DUMP, &obj_array);
+        let utf16 = utf16le(r#"{"msg": "καλημέρα"}"#);
+        let utf16_pos = dump.array(301, TYPE_BYTE, &utf16);
+        dump.flush_heap();
+
+        let latin1: Vec<u8> = r#"{"a": "café"}"#.chars().map(|c| c as u8).collect();
+        let latin1_pos = dump.array(300, TYPE_BYTE, &latin1);
+        let bytes_pos = dump.array(302, TYPE_BYTE, b"xx[1, 2]xx");
+        // Arrays of other primitive types are not carved.
+        dump.array(303, 10, b"[11]");
+        let chars = utf16be(r#"["a", "b"]"#);
+        let chars_pos = dump.array(304, TYPE_CHAR, &chars);
+        dump.flush_heap();
+
+        let string = "java.lang.String".to_string();
+        let expected = vec![
+            (
+                utf16_pos,
+                utf16_pos + utf16.len() - 1,
+                r#"{"msg": "καλημέρα"}"#.to_string(),
+                Encoding::Utf16Le,
+                201,
+                string.clone(),
+            ),
+            (
+                latin1_pos,
+                latin1_pos + latin1.len() - 1,
+                r#"{"a": "café"}"#.to_string(),
+                Encoding::Latin1,
+                200,
+                string,
+            ),
+            (
+                bytes_pos + 2,
+                bytes_pos + 7,
+                "[1, 2]".to_string(),
+                Encoding::Utf8,
+                302,
+                "byte[]".to_string(),
+            ),
+            (
+                chars_pos,
+                chars_pos + chars.len() - 1,
+                r#"["a", "b"]"#.to_string(),
+                Encoding::Utf16Be,
+                304,
+                "char[]".to_string(),
+            ),
+        ];
+        assert_eq!(carve(&dump.buf).unwrap(), expected);
+
+        // A truncated dump is carved up to its last complete sub-record.
+        let truncated = &dump.buf[..chars_pos + 4];
+        assert_eq!(carve(truncated).unwrap(), expected[..3]);
+    }
+
+    #[test]
+    fn test_malformed_hprof() {
+        let error = |input: &[u8]| match carve(input) {
+            Err(errors::Err::HprofError { pos, reason }) => (pos, reason),
+            _ => panic!("the dump is not malformed"),
+        };
+        assert_eq!(error(b"{\"a\": 1}"), (0, "not an HPROF heap dump"));
+        assert_eq!(error(b"JAVA PROFILE 1.0.2"), (0, "truncated header"));
+        assert_eq!(
+            error(b"JAVA PROFILE 1.0.2\0\0\0\0\x02"),
+            (23, "unsupported identifier size")
+        );
+    }
+
+    #[test]
+    fn test_malformed_record() {
+        #[derive(Default)]
+        struct Malformed(Vec<(usize, String)>, Hits);
+
+        impl Sink for Malformed {
+            fn on_hit(&mut self, hit: &Hit) -> io::Result<()> {
+                self.1.on_hit(hit)
+            }
+
+            fn on_malformed(&mut self, pos: usize, reason: &str) -> io::Result<()> {
+                self.0.push((pos, reason.to_string()));
+                Ok(())
+            }
+        }
+
+        // The records after a malformed one are still carved.
+        let mut dump = Dump::new();
+        dump.sub_record(0x42, &[&[0; 8]]);
+        dump.flush_heap();
+        let pos = dump.array(300, TYPE_BYTE, b"[1, 2]");
+        dump.flush_heap();
+        let carver = Carver::new(&dump.buf[..], None, None);
+        let mut sink = Malformed::default();
+        carver.parse_hprof(&dump.buf, &mut sink).unwrap();
+        assert_eq!(sink.0, [(40, "unknown heap dump sub-record".to_string())]);
+        assert_eq!(sink.1.0.len(), 1);
+        assert_eq!(sink.1.0[0].start, pos);
+    }
+
+    #[test]
+    fn test_utf16_byte_order() {
+        let json = r#"{"msg": "καλημέρα"}"#;
+        let order = |data: &[u8]| utf16_byte_order([data].into_iter());
+        assert_eq!(order(&utf16le(json)), Encoding::Utf16Le);
+        assert_eq!(order(&utf16be(json)), Encoding::Utf16Be);
+        assert_eq!(order(&[]), Encoding::Utf16Le);
+    }
+}
@@ -57,7 +57,7 @@
     path_statements,
     redundant_semicolons,
     renamed_and_removed_lints,
-    repr_transparent_external_private_fields,
+    repr_transparent_non_zst_fields,
     semicolon_in_expressions_from_macros,
     special_module_name,
     stable_features,
@@ -69,7 +69,6 @@
     tyvar_behind_raw_pointer,
     uncommon_codepoints,
     unconditional_recursion,
-    undefined_naked_function_abi,
     unexpected_cfgs,
     ungated_async_fn_track_caller,
     uninhabited_static,
@@ -85,45 +84,65 @@
     unused_assignments,
     unused_attributes,
     unused_braces,
-    unused_braces,
     unused_comparisons,
     unused_doc_comments,
     unused_features,
-    unused_features,
     unused_import_braces,
     unused_imports,
-    unused_imports,
-    unused_labels,
     unused_labels,
     unused_macros,
-    unused_macros,
     unused_must_use,
     unused_mut,
-    unused_mut,
-    unused_parens,
     unused_parens,
     unused_qualifications,
     unused_unsafe,
-    unused_unsafe,
     unused_variables,
     warnings,
     while_true
 )]
 
+use std::borrow::Cow;
+use std::collections::VecDeque;
 use std::fs::File;
 use std::io;
 use std::io::{BufRead, BufReader, BufWriter, Read, StderrLock, StdinLock, StdoutLock, Write};
+use std::ops::{ControlFlow, Range};
 
-use memchr;
+use memmap2::Mmap;
 
+mod encoding;
 mod errors;
-
-// Incrementally extend the internal buffer by this amount of bytes, whenever
-// a JSON string no longer fits in it.
-const BUF_EXTEND_SIZE: usize = 4 << 20; // 4MiB
-
-// The maximum identation depth of the JSON string that will be handled.
-const DEFAULT_MAX_IDENT_DEPTH: usize = 4 << 20;
+mod hprof;
+mod literal;
+mod pages;
+mod parallel;
+mod reassemble;
+mod repair;
+mod sanitize;
+mod score;
+mod sink;
+mod spool;
+mod suffix;
+
+pub use encoding::Encoding;
+pub use hprof::HeapObject;
+pub use pages::{Continuation, PageSearch};
+pub use reassemble::{Reassembled, Reassembler};
+pub use repair::{Candidate, DEFAULT_REPAIR_LOOKAHEAD, Edit, Strategy};
+pub use sanitize::Sanitize;
+pub use sink::{CsvReport, JsonLines, RepairLog, Sink};
+pub use spool::Spooled;
+pub use suffix::DEFAULT_SUFFIX_WINDOW;
+
+use score::Features;
+use spool::Spool;
+
+/// The maximum identation depth of the JSON string that will be handled.
+pub const DEFAULT_MAX_IDENT_DEPTH: usize = 4 << 20;
+
+// The initial capacity of the identation stack. It grows as needed, up to
+// the maximum identation depth.
+const IDENT_STACK_SIZE: usize = 64;
 
 /// The minimum size of a JSON string that we will report.
 pub const DEFAULT_MIN_JSON_SIZE: usize = 4;
@@ -172,27 +191,102 @@ const CHAR_U: u8 = 0x75; // u
 
 enum Cause {
     Found(u8),
-    Corrupted(u8),
+    Corrupted,
     Completed,
     Exhausted,
+    TooDeep,
+    TooLarge,
+    // The unmatched closing brackets have ended, so the JSON string that
+    // they close can be recovered.
+    Suffix,
+    // A comma follows the unmatched closing brackets, so more of them may
+    // follow.
+    Continued,
 }
 
 fn byte_needs_escape(b: u8) -> bool {
-    b < 0x1F
+    b <= 0x1F
 }
 
 fn byte_can_escape(b: u8) -> bool {
-    match b {
+    matches!(
+        b,
         CHAR_QUOT_MARK
-        | CHAR_ESCAPE
-        | CHAR_SLASH
-        | CHAR_ESC_BACKSPACE
-        | CHAR_ESC_FORM_FEED
-        | CHAR_ESC_LINE_FEED
-        | CHAR_ESC_CARRIAGE_RETURN
-        | CHAR_ESC_TAB
-        | CHAR_U => true,
-        _ => false,
+            | CHAR_ESCAPE
+            | CHAR_SLASH
+            | CHAR_ESC_BACKSPACE
+            | CHAR_ESC_FORM_FEED
+            | CHAR_ESC_LINE_FEED
+            | CHAR_ESC_CARRIAGE_RETURN
+            | CHAR_ESC_TAB
+            | CHAR_U
+    )
+}
+
+// The range of the UTF-8 continuation bytes.
+const UTF8_CONTINUATION: (u8, u8) = (0x80, 0xBF);
+
+// Return the number of continuation bytes that follow the first byte of a
+// UTF-8 character, and the range of the first of them, so that overlong
+// encodings and encoded surrogates are rejected (see RFC 3629, section 4).
+// Return `None` if no character starts with the byte.
+fn utf8_lead(b: u8) -> Option<(u8, (u8, u8))> {
+    match b {
+        0x00..=0x7F => Some((0, UTF8_CONTINUATION)),
+        0xC2..=0xDF => Some((1, UTF8_CONTINUATION)),
+        0xE0 => Some((2, (0xA0, 0xBF))),
+        0xE1..=0xEC | 0xEE..=0xEF => Some((2, UTF8_CONTINUATION)),
+        0xED => Some((2, (0x80, 0x9F))),
+        0xF0 => Some((3, (0x90, 0xBF))),
+        0xF1..=0xF3 => Some((3, UTF8_CONTINUATION)),
+        0xF4 => Some((3, (0x80, 0x8F))),
+        _ => None,
+    }
+}
+
+fn is_whitespace(b: u8) -> bool {
+    matches!(
+        b,
+        CHAR_SPACE | CHAR_TAB | CHAR_NEWLINE | CHAR_CARRIAGE_RETURN
+    )
+}
+
+// Newlines can only appear as whitespace, so they can be replaced without
+// changing the meaning of a JSON string.
+fn replace_newlines(buf: &mut [u8]) {
+    for b in buf.iter_mut().filter(|b| **b == CHAR_NEWLINE) {
+        *b = CHAR_SPACE;
+    }
+}
+
+// Return the length of `buf` without the incomplete UTF-8 character that it
+// may end with.
+fn utf8_complete_len(buf: &[u8]) -> usize {
+    // Look for the first byte of the last character.
+    for (i, &b) in buf.iter().enumerate().rev() {
+        let len = match b {
+            0x00..=0x7F => 1,
+            0x80..=0xBF => continue,
+            0xC0..=0xDF => 2,
+            0xE0..=0xEF => 3,
+            _ => 4,
+        };
+        return match i + len > buf.len() {
+            true => i,
+            false => buf.len(),
+        };
+    }
+    buf.len()
+}
+
+// Return the `range` of `data`, borrowing it if `data` is borrowed.
+fn slice_cow<'t>(data: &Cow<'t, [u8]>, range: Range<usize>) -> Cow<'t, [u8]> {
+    match data {
+        Cow::Borrowed(data) => {
+            let data: &'t [u8] = data;
+            Cow::Borrowed(&data[range])
+        }
+        Cow::Owned(data) => Cow::Owned(data[range].to_vec()),
     }
 }
 
@@ -200,120 +294,523 @@ fn _closing_ident(b: u8) -> u8 {
     b + 0x02
 }
 
-struct Report {
-    status: Cause,
-    start: usize,
-    end: usize,
-    partial_end: usize,
+/// The outcome of carving a JSON string out of the byte stream.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Status {
+    /// The JSON string is structurally valid.
+    Completed,
+    /// The JSON string was interrupted by a byte that cannot appear at that
+    /// position.
+    Corrupted,
+    /// The byte stream ended before the JSON string did.
+    Exhausted,
+    /// The JSON string was nested deeper than the maximum identation depth
+    /// of the carver.
+    TooDeep,
+    /// The JSON string was larger than the maximum size of the carver.
+    TooLarge,
+    /// The JSON string is structurally valid, and was found nested within a
+    /// JSON string that did not complete.
+    Salvaged,
+    /// The JSON string is the end of a JSON string whose beginning was
+    /// overwritten. Its missing brackets have been re-opened in
+    /// [`Hit::repaired`].
+    SuffixRecovered,
+    /// The JSON string was interrupted by the first byte of a page, in a
+    /// memory image (see [`Carver::page_size`]). The rest of it may be in
+    /// another page.
+    PageBoundary,
+    /// The JSON string is a string literal outside of any array or object,
+    /// with valid escape sequences and UTF-8 (see [`Carver::strings`]).
+    String,
+    /// The JSON string was interrupted by a string that is not valid
+    /// Unicode, i.e., by invalid UTF-8 or by an unpaired surrogate escape
+    /// (see [`Carver::strict`]).
+    InvalidUnicode,
 }
 
-impl Report {
-    /// Print a status report.
-    ///
-    /// Status reports are comma-separated CSVs with the following fields:
-    ///
-    /// ```text
-    /// status,start,end,partial_end
-    /// ```
+impl Status {
+    /// The name of the status, as it appears in reports.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Status::Completed => "completed",
+            Status::Corrupted => "corrupted",
+            Status::Exhausted => "exhausted",
+            Status::TooDeep => "too_deep",
+            Status::TooLarge => "too_large",
+            Status::Salvaged => "salvaged",
+            Status::SuffixRecovered => "suffix_recovered",
+            Status::PageBoundary => "page_boundary",
+            Status::String => "string",
+            Status::InvalidUnicode => "invalid_unicode",
+        }
+    }
+}
+
+/// How the carver repairs incomplete JSON strings (see
+/// [`Carver::fix_incomplete`]).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum RepairMode {
+    /// Truncate the JSON string right after its last bracket, and close the
+    /// brackets that are still open.
+    #[default]
+    Brackets,
+    /// Truncate the JSON string right after its last complete value, or
+    /// close the string value that it stopped in, and close the brackets
+    /// that are still open. This keeps as much of the content as possible.
+    Values,
+}
+
+/// The type of the outermost value of a JSON string (see
+/// [`Carver::top_level`]).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum TopLevel {
+    /// The JSON string is an object, e.g., `{"a": 1}`.
+    Object,
+    /// The JSON string is an array, e.g., `[1, 2]`.
+    Array,
+}
+
+/// A JSON string that was carved out of the byte stream.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Hit<'a> {
+    /// Whether the JSON string is complete or not.
+    pub status: Status,
+    /// The position of the first character of the JSON string within the
+    /// byte stream.
+    pub start: usize,
+    /// The position of the last character of the JSON string within the byte
+    /// stream.
+    pub end: usize,
+    /// The position of the last character where the JSON string could have
+    /// ended.
+    pub partial_end: usize,
+    /// The carved bytes, as they were processed by the carver.
     ///
-    /// where:
-    /// * `status` is either "corrupted", "exhausted", or "completed".
-    /// * (`start`, `end`) is the position of the JSON string within the byte
-    ///   stream, last character included.
-    /// * `partial_end` is the position of the last character where the JSON
-    ///    string could have ended.
-    fn print(&self, writer: &mut Writer) -> Result<(), errors::Err> {
-        let w = writer.mut_ref();
-
-        let status = match self.status {
-            Cause::Exhausted => "exhausted",
-            Cause::Corrupted(_) => "corrupted",
-            Cause::Completed => "completed",
-            _ => unreachable!(),
+    /// If the carver did not have to transform them, they are borrowed from
+    /// the buffer that the carver reads from, whenever possible.
+    pub data: Cow<'a, [u8]>,
+    /// An incomplete, but structurally valid, version of the JSON string. Set
+    /// only for corrupted/exhausted strings, if the carver was asked to fix
+    /// them, and for recovered suffixes.
+    pub repaired: Option<Vec<u8>>,
+    /// The first bytes of the JSON string, if it grew past the spool
+    /// threshold of the carver and they were moved to a spool file. In that
+    /// case, `data` and `repaired` hold only the bytes that follow them.
+    pub spooled: Option<Spooled<'a>>,
+    /// The ways in which the JSON string can be repaired, best first. Set
+    /// only for JSON strings that did not complete, if the carver was asked
+    /// to rank repairs, and they have not been spooled.
+    pub candidates: Vec<Candidate>,
+    /// How likely it is that the JSON string is not a false positive, from 0
+    /// to 100. Set only if the carver was asked to score JSON strings (see
+    /// [`Carver::min_score`]).
+    pub score: Option<u8>,
+    /// The position of the JSON string that this one is nested within. Set
+    /// only if the carver was asked to carve exhaustively (see
+    /// [`Carver::exhaustive`]), and this JSON string starts within another
+    /// one.
+    pub parent: Option<usize>,
+    /// The encoding that the JSON string was found in, before it was
+    /// transcoded to UTF-8. Set only if the carver was asked to carve other
+    /// encodings than UTF-8 (see [`Carver::parse_encoded`]).
+    pub encoding: Option<Encoding>,
+    /// The object of a Java heap dump that the JSON string was found in. Set
+    /// only if the carver has carved a heap dump (see
+    /// [`Carver::parse_hprof`]).
+    pub object: Option<HeapObject>,
+}
+
+impl Hit<'_> {
+    /// Copy the carved bytes, so that the hit no longer borrows the buffer
+    /// that the carver reads from. Spooled bytes are read back into memory.
+    pub fn into_owned(self) -> io::Result<Hit<'static>> {
+        let (data, repaired) = match &self.spooled {
+            None => (self.data.into_owned(), self.repaired),
+            Some(spooled) => {
+                let mut data = Vec::with_capacity(spooled.len() + self.data.len());
+                spooled.copy_to(spooled.len(), &mut data)?;
+                data.extend_from_slice(&self.data);
+                let repaired = match self.repaired {
+                    Some(tail) => {
+                        let mut repaired = vec![];
+                        spooled.copy_to(spooled.repaired_len(), &mut repaired)?;
+                        repaired.extend_from_slice(&tail);
+                        Some(repaired)
+                    }
+                    None => None,
+                };
+                (data, repaired)
+            }
         };
-        w.write_all(
-            format!(
-                "{},{},{},{}\n",
-                status, self.start, self.end, self.partial_end
-            )
-            .as_ref(),
-        )?;
-        Ok(())
+        Ok(Hit {
+            status: self.status,
+            start: self.start,
+            end: self.end,
+            partial_end: self.partial_end,
+            data: Cow::Owned(data),
+            repaired,
+            spooled: None,
+            candidates: self.candidates,
+            score: self.score,
+            parent: self.parent,
+            encoding: self.encoding,
+            object: self.object,
+        })
+    }
+
+    /// The number of bytes that the JSON string spans in the byte stream.
+    pub fn len(&self) -> usize {
+        self.end - self.start + 1
+    }
+
+    /// Whether the JSON string spans no bytes. Always false, since every
+    /// carved string starts with a bracket or a quote.
+    pub fn is_empty(&self) -> bool {
+        false
     }
+
+    /// Return the page boundaries that the JSON string crosses, i.e., the
+    /// positions of the first bytes of the pages that it continues in.
+    pub fn page_boundaries(&self, page_size: usize) -> impl Iterator<Item = usize> + use<> {
+        let page_size = page_size.max(1);
+        let first = self.start / page_size + 1;
+        let last = self.end / page_size;
+        (first..=last).map(move |page| page * page_size)
+    }
+}
+
+// The states of the JSON state machine. Each state describes which bytes
+// are accepted next.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum State {
+    // Looking for the start of a JSON string.
+    Scout,
+    // Right after a closing bracket that no opening bracket matches.
+    Orphan,
+    // Right after a left square bracket.
+    ArrayStart,
+    // Right after a left curly bracket.
+    ObjectStart,
+    // Right after a value that is not a string or a number.
+    ValueEnd,
+    // Right after a colon.
+    Colon,
+    // Right after a comma within an array.
+    ArrayComma,
+    // Right after a comma within an object.
+    ObjectComma,
+    // Within a string, or right after it if it's `closed`.
+    String {
+        closed: bool,
+        in_escape: bool,
+        in_escaped_unicode: u8,
+    },
+    // Within a number, whose last byte is `prev`.
+    Number {
+        prev: u8,
+        in_frac: bool,
+        in_exp: bool,
+        in_leading_zero: Option<bool>,
+    },
+    // Within a literal, with the remaining bytes of the literal.
+    Literal(&'static [u8]),
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 struct JsonTracker {
     cur: usize,
     partial_close_end: usize,
     ident_levels: Vec<u8>,
-    cur_ident_level: usize,
+    // The positions of the open brackets in `ident_levels`, relative to the
+    // start of the JSON string.
+    ident_starts: Vec<usize>,
+    max_ident_depth: usize,
+    // The position right after the last value or bracket that has completed,
+    // and the identation depth at that point. The JSON string can be
+    // truncated there, without leaving a dangling key or comma behind.
+    value_end: usize,
+    value_depth: usize,
+    // The position of the opening quote of the last string.
+    string_start: usize,
+    // The number of bytes to look back at, when recovering the suffix of a
+    // JSON string. If not set, unmatched closing brackets are ignored.
+    suffix_window: Option<usize>,
+    // The number of bytes to look ahead at, past the byte that corrupts a
+    // JSON string, so that its repairs can be ranked. If not set, repairs are
+    // not ranked.
+    repair_lookahead: Option<usize>,
+    // The position of the byte that corrupted the current JSON string,
+    // relative to its start, while the carver looks ahead past it. The state
+    // is left as it was at that byte.
+    corrupted_at: Option<usize>,
+    // The maximum number of bytes of a JSON string.
+    max_size: usize,
+    // The minimum number of characters of the string literals outside of
+    // arrays and objects that are carved. If not set, they are ignored.
+    min_string_len: Option<usize>,
+    // The position in the stream before which quotes do not start string
+    // literals, since they belong to one that is scanned again.
+    quotes_from: usize,
     in_key: bool,
+    // Whether strings must be valid Unicode, i.e., valid UTF-8 without
+    // unpaired surrogate escapes.
+    strict: bool,
+    // The number of UTF-8 continuation bytes that the current string still
+    // needs, and the range of the next one, if the carver is strict.
+    utf8_needed: u8,
+    utf8_range: (u8, u8),
+    // The code unit of the `\uXXXX` escape sequence that is being parsed, and
+    // whether it follows a high surrogate, if the carver is strict.
+    escaped_unit: u16,
+    in_surrogate_pair: bool,
+    // Whether the current JSON string was corrupted by invalid Unicode.
+    invalid_unicode: bool,
+    // Whether to keep track of the nested JSON strings that have completed.
+    salvage: bool,
+    // Whether to scan every JSON string again, right after its first byte, so
+    // that the JSON strings that start within it are found as well.
+    exhaustive: bool,
+    // The position of the last JSON string that does not start within
+    // another one, as (start, end), if the carver carves exhaustively.
+    outer: Option<(usize, usize)>,
+    // The outermost nested JSON strings that have completed, as (start, end)
+    // positions relative to the start of the JSON string, along with their
+    // features.
+    salvaged: Vec<(usize, usize, Features)>,
+    // The features of the current JSON string, for scoring it.
+    features: Features,
+    // The features of the current JSON string at the point where each bracket
+    // in `ident_levels` was opened. Their depth is the maximum depth that has
+    // been reached since then, and their members are the members of the
+    // bracket itself.
+    ident_features: Vec<Features>,
+    // Whether the key that is being parsed looks like a name so far.
+    key_plausible: bool,
+    // The first bytes of the current JSON string, if it did not fit in the
+    // buffers that it was fed with.
     processed: Vec<u8>,
-    replace_newlines: bool,
+    // The number of bytes of the current JSON string that have been moved
+    // from `processed` to the spool file.
+    spooled: usize,
+    state: State,
+    // The position of the next byte in the stream.
+    pos: usize,
+    // The position of the current JSON string in the stream.
+    start: usize,
 }
 
-impl JsonTracker {
-    fn new(max_size: Option<usize>, max_ident_depth: Option<usize>) -> JsonTracker {
-        let _max_size = match max_size {
-            Some(size) => size,
-            None => BUF_EXTEND_SIZE,
-        };
+/// The part of the state of a [`JsonTracker`] that decides which bytes it
+/// accepts next (see [`JsonTracker::expecting`]).
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+struct Expecting {
+    state: State,
+    last_ident: Option<u8>,
+    // Whether another bracket can be opened, and whether the JSON string can
+    // grow by another byte.
+    deeper: bool,
+    larger: bool,
+    in_key: bool,
+    utf8: (u8, (u8, u8)),
+    escaped_unit: u16,
+    in_surrogate_pair: bool,
+}
+
+/// The hits for a JSON string that has stopped.
+#[derive(Debug, Default)]
+struct Resolved<'t> {
+    /// The JSON string itself, if it should be reported.
+    hit: Option<Hit<'t>>,
+    /// The nested JSON strings that could be salvaged from it.
+    salvaged: Vec<Hit<'t>>,
+}
+
+impl<'t> Resolved<'t> {
+    /// Iterate over the hits, in the order of their positions.
+    fn iter(&self) -> impl Iterator<Item = &Hit<'t>> {
+        self.hit.iter().chain(&self.salvaged)
+    }
+}
 
+impl<'t> IntoIterator for Resolved<'t> {
+    type Item = Hit<'t>;
+    type IntoIter = std::iter::Chain<std::option::IntoIter<Hit<'t>>, std::vec::IntoIter<Hit<'t>>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.hit.into_iter().chain(self.salvaged)
+    }
+}
+
+impl JsonTracker {
+    fn new(buf_size: Option<usize>, max_ident_depth: Option<usize>) -> JsonTracker {
+        // A JSON string is at least one level deep.
         let _max_ident_depth = match max_ident_depth {
-            Some(size) => size,
+            Some(size) => size.max(1),
             None => DEFAULT_MAX_IDENT_DEPTH,
         };
         JsonTracker {
             cur: 0,
             partial_close_end: 0,
-            ident_levels: vec![0u8; _max_ident_depth],
-            cur_ident_level: 0,
+            ident_levels: Vec::with_capacity(IDENT_STACK_SIZE.min(_max_ident_depth)),
+            ident_starts: Vec::with_capacity(IDENT_STACK_SIZE.min(_max_ident_depth)),
+            max_ident_depth: _max_ident_depth,
+            value_end: 0,
+            value_depth: 0,
+            string_start: 0,
+            suffix_window: None,
+            repair_lookahead: None,
+            corrupted_at: None,
+            max_size: usize::MAX,
+            min_string_len: None,
+            quotes_from: 0,
             in_key: false,
-            processed: vec![0u8; _max_size],
-            replace_newlines: false,
+            strict: false,
+            utf8_needed: 0,
+            utf8_range: UTF8_CONTINUATION,
+            escaped_unit: 0,
+            in_surrogate_pair: false,
+            invalid_unicode: false,
+            salvage: false,
+            exhaustive: false,
+            outer: None,
+            salvaged: vec![],
+            features: Features::default(),
+            ident_features: Vec::with_capacity(IDENT_STACK_SIZE.min(_max_ident_depth)),
+            key_plausible: false,
+            processed: Vec::with_capacity(buf_size.unwrap_or(0)),
+            spooled: 0,
+            state: State::Scout,
+            pos: 0,
+            start: 0,
         }
     }
 
-    fn advance(&mut self, mut b: u8) {
-        // See how ripgrep handles the "very large lines" problem:
-        // https://github.com/BurntSushi/ripgrep/issues/2959
-        // FIXME: Handle the case where we are asked to advance, but there is
-        // no identation level remaining.
-        //
-        if self.replace_newlines && b == CHAR_NEWLINE {
-            b = CHAR_SPACE;
-        }
-        if self.cur < self.processed.len() {
-            self.processed[self.cur] = b;
-        } else {
-            self.processed.reserve(BUF_EXTEND_SIZE);
-            self.processed.push(b);
-        }
+    /// Create a tracker for scanning the JSON strings that this one has
+    /// stopped again, with the settings that decide where they stop.
+    fn rescanner(&self) -> JsonTracker {
+        let mut jt = JsonTracker::new(Some(0), Some(self.max_ident_depth));
+        jt.max_size = self.max_size;
+        jt.strict = self.strict;
+        jt
+    }
+
+    /// Scan a JSON string that has stopped, or a part of it, again from its
+    /// start, with the settings of this tracker (see
+    /// [`JsonTracker::rescanner`]), e.g., to get the state of the parser at
+    /// its end, or its features. Return the tracker that scanned it, along
+    /// with the cause that stopped it, if any.
+    fn rescan(&self, data: &[u8]) -> (JsonTracker, Option<Cause>) {
+        let mut jt = self.rescanner();
+        let (_, cause) = jt.feed(data);
+        (jt, cause)
+    }
+
+    fn advance(&mut self) {
         self.cur += 1;
     }
 
-    fn last_byte(&self) -> Option<u8> {
-        if self.cur == 0 {
-            return None;
+    /// Advance through multiple bytes at once.
+    fn advance_by(&mut self, n: usize) {
+        self.cur += n;
+    }
+
+    /// The number of bytes of the current JSON string that have been copied
+    /// out of the buffers that it was fed with.
+    fn spilled(&self) -> usize {
+        self.spooled + self.processed.len()
+    }
+
+    /// Copy the bytes of the current JSON string that have been fed so far,
+    /// since the buffer that holds them is about to go away.
+    ///
+    /// `consumed` is the part of the buffer that the state machine has
+    /// consumed. If the copied bytes grow past the spool threshold, they are
+    /// moved to `spool`.
+    fn spill(&mut self, consumed: &[u8], spool: &mut Spool, opts: &Options) -> io::Result<()> {
+        // Let the buffer grow on demand, so that carvers (and their forks)
+        // that never see a string span reads don't pay for it. See how
+        // ripgrep handles the "very large lines" problem:
+        // https://github.com/BurntSushi/ripgrep/issues/2959
+        let n = self.cur - self.spilled();
+        self.processed
+            .extend_from_slice(&consumed[consumed.len() - n..]);
+
+        match opts.spool_threshold {
+            // String literals are decoded once they complete, so they are
+            // kept in memory. Their size is bounded by the maximum size.
+            _ if self.is_literal() => Ok(()),
+            Some(threshold) if self.processed.len() >= threshold => {
+                if opts.replace_newlines {
+                    replace_newlines(&mut self.processed);
+                }
+                spool.write(&self.processed, self.spooled == 0)?;
+                self.spooled += self.processed.len();
+                self.processed.clear();
+                Ok(())
+            }
+            _ => Ok(()),
         }
+    }
 
-        Some(self.processed[self.cur - 1])
+    /// Return the bytes of the current JSON string that have not been
+    /// spooled.
+    ///
+    /// `consumed` is the part of the buffer that the state machine has
+    /// consumed. If the JSON string was fed in a single buffer, the bytes are
+    /// borrowed from it instead of being copied.
+    fn data<'t>(&self, consumed: &'t [u8]) -> Cow<'t, [u8]> {
+        let n = self.cur - self.spilled();
+        let tail = &consumed[consumed.len() - n..];
+        match self.processed.is_empty() {
+            true => Cow::Borrowed(tail),
+            false => Cow::Owned([&self.processed[..], tail].concat()),
+        }
     }
 
     fn last_ident(&self) -> Option<u8> {
-        if self.cur_ident_level == 0 {
-            return None;
-        }
+        self.ident_levels.last().copied()
+    }
 
-        Some(self.ident_levels[self.cur_ident_level - 1])
+    /// Return the part of the state that decides which bytes are accepted
+    /// next, regardless of the position of the JSON string.
+    fn expecting(&self) -> Expecting {
+        Expecting {
+            state: self.state,
+            last_ident: self.last_ident(),
+            deeper: self.ident_levels.len() < self.max_ident_depth,
+            larger: self.cur < self.max_size,
+            in_key: self.in_key,
+            utf8: (self.utf8_needed, self.utf8_range),
+            escaped_unit: self.escaped_unit,
+            in_surrogate_pair: self.in_surrogate_pair,
+        }
     }
 
-    fn add_ident(&mut self, b: u8) {
-        self.cur_ident_level += 1;
-        self.ident_levels[self.cur_ident_level - 1] = b;
+    fn add_ident(&mut self, b: u8) -> Result<(), ()> {
+        if self.ident_levels.len() == self.max_ident_depth {
+            return Err(());
+        }
+
+        if self.ident_levels.is_empty() {
+            self.features.object = b == CHAR_LEFT_CURLY_BRACKET;
+        }
+        self.ident_levels.push(b);
+        self.ident_starts.push(self.cur);
+        let depth = self.ident_levels.len();
+        self.features.depth = self.features.depth.max(depth);
+        self.ident_features.push(Features {
+            object: b == CHAR_LEFT_CURLY_BRACKET,
+            depth,
+            members: 0,
+            ..self.features
+        });
         self.partial_close_end = self.cur;
-        self.advance(b);
+        self.advance();
+        self.mark_value_end();
+        Ok(())
     }
 
     fn remove_ident(&mut self, expected: u8) -> Result<bool, ()> {
@@ -327,24 +824,1092 @@ impl JsonTracker {
         }
 
         self.partial_close_end = self.cur;
-        self.cur_ident_level -= 1;
-        self.advance(_closing_ident(expected)); // That's the closing bracket.
+        self.ident_levels.pop();
+        let start = self.ident_starts.pop().unwrap();
+        let opened = self.ident_features.pop().unwrap();
+        if let Some(parent) = self.ident_features.last_mut() {
+            parent.depth = parent.depth.max(opened.depth);
+        }
+        if self.salvage && !self.ident_levels.is_empty() {
+            // The nested JSON strings that this one contains are no longer
+            // the outermost ones.
+            while self.salvaged.last().is_some_and(|&(s, _, _)| s > start) {
+                self.salvaged.pop();
+            }
+            let features = Features {
+                object: opened.object,
+                depth: opened.depth - self.ident_levels.len(),
+                members: opened.members,
+                ..self.features.since(&opened)
+            };
+            self.salvaged.push((start, self.cur, features));
+        }
+        self.advance(); // That's the closing bracket.
+        self.mark_value_end();
+
+        Ok(self.ident_levels.is_empty())
+    }
+
+    /// Count a key of the innermost object, or an element of the innermost
+    /// array, that has just started.
+    fn add_member(&mut self) {
+        if self.ident_levels.len() == 1 {
+            self.features.members += 1;
+        }
+        if let Some(opened) = self.ident_features.last_mut() {
+            opened.members += 1;
+        }
+    }
 
-        match self.cur_ident_level {
-            0 => Ok(true),
-            _ => Ok(false),
+    /// Count a value that has just started, if it's an element of an array.
+    /// The values of objects are counted by their keys.
+    fn add_array_member(&mut self) {
+        if self.last_ident() == Some(CHAR_LEFT_SQUARE_BRACKET) {
+            self.add_member();
         }
     }
 
-    fn quick_clean(&mut self) -> () {
+    fn quick_clean(&mut self) {
         self.cur = 0;
         self.partial_close_end = 0;
-        self.cur_ident_level = 0;
+        self.value_end = 0;
+        self.value_depth = 0;
+        self.string_start = 0;
+        self.ident_levels.clear();
+        self.ident_starts.clear();
+        self.salvaged.clear();
+        self.features = Features::default();
+        self.ident_features.clear();
+        self.key_plausible = false;
+        self.corrupted_at = None;
         self.in_key = false;
+        self.utf8_needed = 0;
+        self.utf8_range = UTF8_CONTINUATION;
+        self.escaped_unit = 0;
+        self.in_surrogate_pair = false;
+        self.invalid_unicode = false;
+        self.processed.clear();
+        self.spooled = 0;
+        self.state = State::Scout;
+    }
+
+    /// Whether we are in the middle of a JSON string.
+    fn in_progress(&self) -> bool {
+        self.state != State::Scout
+    }
+
+    /// Whether the current JSON string is a string literal outside of any
+    /// array or object.
+    fn is_literal(&self) -> bool {
+        // Arrays and objects are at least one level deep, even once they
+        // have been closed.
+        self.features.depth == 0 && matches!(self.state, State::String { .. })
+    }
+
+    /// Remember that the JSON string can be truncated at the current
+    /// position, since a value or a bracket has just completed.
+    fn mark_value_end(&mut self) {
+        self.value_end = self.cur;
+        self.value_depth = self.ident_levels.len();
+    }
+
+    /// Return the repaired version of the JSON string, without the spooled
+    /// bytes that it starts with, and the number of these bytes.
+    fn repair_incomplete(&self, data: &[u8], mode: RepairMode) -> (Vec<u8>, usize) {
+        let (end, depth, quote) = self.repair_point(data, mode);
+        let spooled = end.min(self.spooled);
+        let mut buf = data[..end - spooled].to_vec();
+        buf.extend(quote);
+        for &ident in self.ident_levels[..depth].iter().rev() {
+            buf.push(_closing_ident(ident));
+        }
+        (buf, spooled)
+    }
+
+    /// Return where the JSON string is truncated when it's repaired, the
+    /// number of brackets that are closed afterwards, and the quote that
+    /// closes the string value that it stopped in, if any.
+    fn repair_point(&self, data: &[u8], mode: RepairMode) -> (usize, usize, Option<u8>) {
+        match mode {
+            RepairMode::Brackets => (self.partial_close_end + 1, self.ident_levels.len(), None),
+            RepairMode::Values => match self.open_string_end(data) {
+                Some(end) => (end, self.ident_levels.len(), Some(CHAR_QUOT_MARK)),
+                None => (self.value_end, self.value_depth, None),
+            },
+        }
+    }
+
+    /// The size of an incomplete JSON string, up to the last byte that its
+    /// repaired version keeps.
+    fn kept_size(&self, mode: RepairMode) -> usize {
+        match mode {
+            RepairMode::Brackets => self.partial_close_end,
+            RepairMode::Values => match self.state {
+                State::String { closed: false, .. } if !self.in_key => self.cur - 1,
+                _ => self.value_end - 1,
+            },
+        }
+    }
+
+    /// Return the position where the string value that the JSON string
+    /// stopped in can be closed, if any. Incomplete escape sequences,
+    /// surrogate pairs and UTF-8 characters at its end are dropped.
+    fn open_string_end(&self, data: &[u8]) -> Option<usize> {
+        let State::String {
+            closed: false,
+            in_escape,
+            in_escaped_unicode,
+        } = self.state
+        else {
+            return None;
+        };
+        // A key without a value cannot be kept anyway.
+        if self.in_key {
+            return None;
+        }
+        let end = match (in_escape, in_escaped_unicode) {
+            (true, _) => self.cur - 1,
+            (_, 1..=4) => self.cur - 2 - (4 - in_escaped_unicode as usize),
+            _ => self.cur,
+        };
+        // So is a high surrogate escape that has not been paired yet.
+        let end = match self.in_surrogate_pair {
+            true => end - 6,
+            false => end,
+        };
+        // The last UTF-8 character must not be spooled, so that we can check
+        // it.
+        let from = (self.string_start + 1).max(end.saturating_sub(3));
+        if from < self.spooled {
+            return None;
+        }
+        let tail = &data[from - self.spooled..end - self.spooled];
+        Some(from + utf8_complete_len(tail))
+    }
+
+    /// Create the hits for the JSON string that has just stopped, if it should
+    /// be reported, and for the JSON strings that were salvaged from it, and
+    /// prepare for the next one.
+    ///
+    /// `consumed` is the part of the buffer that the state machine has
+    /// consumed, and `spool` is the spool file, if the JSON string may have
+    /// been spooled. `lookback` holds the bytes that the state machine has
+    /// consumed most recently, for scoring the JSON strings.
+    fn resolve<'t>(
+        &mut self,
+        cause: Cause,
+        consumed: &'t [u8],
+        spool: Option<&'t File>,
+        lookback: &[u8],
+        opts: &Options,
+    ) -> Resolved<'t> {
+        if let Cause::Continued = cause {
+            self.quick_clean();
+            return Resolved::default();
+        }
+        let start = self.start;
+        let lookback_end = self.pos;
+        let rescan = self.rewind(&cause, consumed);
+        // If the carver has looked ahead past the byte that corrupted the
+        // JSON string, the JSON string stops at that byte. The bytes that
+        // follow are used only for ranking its repairs.
+        let mut seen = None;
+        if let Some(at) = self.corrupted_at {
+            seen = Some((self.data(consumed), self.spooled));
+            self.cur = at;
+            self.spooled = self.spooled.min(at);
+            // Scan the bytes that follow again, since they may contain the
+            // next JSON string.
+            self.pos = start + at;
+        }
+        if self.is_literal() {
+            // The quotes up to where the string literal stopped are not
+            // scanned again, if it has to be, since they have been matched.
+            self.quotes_from = start + self.cur;
+            if let Some(from) = rescan {
+                self.pos = start + from;
+            }
+        }
+        let end = start + self.cur - 1;
+        let (status, partial_end, size) = match cause {
+            Cause::Completed => (Status::Completed, end, self.cur),
+            Cause::Corrupted => (
+                match opts.page_size {
+                    Some(size) if (start + self.cur).is_multiple_of(size) => Status::PageBoundary,
+                    _ if self.invalid_unicode => Status::InvalidUnicode,
+                    _ => Status::Corrupted,
+                },
+                start + self.partial_close_end,
+                self.kept_size(opts.repair_mode),
+            ),
+            Cause::Exhausted => (
+                Status::Exhausted,
+                start + self.partial_close_end,
+                self.kept_size(opts.repair_mode),
+            ),
+            Cause::TooDeep => (
+                Status::TooDeep,
+                start + self.partial_close_end,
+                self.kept_size(opts.repair_mode),
+            ),
+            Cause::TooLarge => {
+                // Resume right after its start, since it may contain
+                // smaller JSON strings.
+                self.pos = start + 1;
+                (
+                    Status::TooLarge,
+                    start + self.partial_close_end,
+                    self.kept_size(opts.repair_mode),
+                )
+            }
+            Cause::Found(_) | Cause::Suffix | Cause::Continued => unreachable!(),
+        };
+        let literal = self.is_literal();
+        let status = match (status, literal) {
+            (Status::Completed, true) => Status::String,
+            (status, _) => status,
+        };
+        // In exhaustive mode, every JSON string is scanned again, right after
+        // its start, since the JSON strings that start within it have to be
+        // found as well. Only the structurally valid ones are reported.
+        let parent = match self.outer {
+            Some((outer_start, outer_end)) if (outer_start + 1..=outer_end).contains(&start) => {
+                Some(outer_start)
+            }
+            _ => None,
+        };
+        if self.exhaustive {
+            self.pos = start + 1;
+            if parent.is_none() {
+                self.outer = Some((start, end));
+            }
+        }
+
+        // JSON strings that are too large, or that are carved exhaustively,
+        // are scanned again, so the JSON strings within them will be found
+        // anyway.
+        let salvage = !matches!(status, Status::Completed | Status::TooLarge);
+        let salvage = salvage && !self.exhaustive && !self.salvaged.is_empty();
+        let score = opts.min_score.map(|_| {
+            let context = score::context(lookback, lookback_end, start);
+            score::score(&self.features, size, context)
+        });
+        // String literals have no structure, and they are reported only if
+        // they have completed outside of any other JSON string.
+        let nested_ok = match (literal, parent) {
+            (true, None) => status == Status::String,
+            (true, Some(_)) => false,
+            (false, None) => true,
+            (false, Some(_)) => status == Status::Completed,
+        };
+        // The score is set only if there is a minimum score.
+        let mut keep = size >= opts.min_size
+            && score >= opts.min_score
+            && (literal || opts.keeps_structure(&self.features))
+            && nested_ok;
+        let mut resolved = Resolved::default();
+        if keep || salvage {
+            let mut data = match &seen {
+                None => self.data(consumed),
+                Some((seen, spooled)) => slice_cow(seen, 0..self.cur - self.cur.min(*spooled)),
+            };
+            // Repairs are ranked before the newlines are replaced, since they
+            // have to scan the JSON string again.
+            let candidates = match (self.repair_lookahead, &seen) {
+                _ if matches!(status, Status::Completed | Status::String) || !keep => vec![],
+                (Some(_), None) if self.spooled == 0 => {
+                    self.candidates(&data, opts.replace_newlines)
+                }
+                (Some(_), Some((seen, 0))) => self.candidates(seen, opts.replace_newlines),
+                _ => vec![],
+            };
+            // Copy the JSON string only if it has newlines to replace.
+            if opts.replace_newlines && memchr::memchr(CHAR_NEWLINE, &data).is_some() {
+                replace_newlines(data.to_mut());
+            }
+            if salvage {
+                resolved.salvaged =
+                    self.salvaged_hits(&data, spool, (lookback, lookback_end), opts);
+            }
+            // String literals are never spooled, so they can be decoded.
+            if literal {
+                let min_len = self.min_string_len.unwrap_or(0);
+                keep = literal::decode(&data).is_some_and(|text| text.chars().count() >= min_len);
+            }
+            if !keep {
+                self.quick_clean();
+                return resolved;
+            }
+            let (repaired, repaired_spooled) = match status {
+                Status::Completed | Status::String => (None, 0),
+                _ if opts.fix_incomplete => {
+                    let (repaired, spooled) = self.repair_incomplete(&data, opts.repair_mode);
+                    (Some(repaired), spooled)
+                }
+                _ => (None, 0),
+            };
+            let spooled = match (self.spooled, spool) {
+                (0, _) | (_, None) => None,
+                (len, Some(file)) => Some(Spooled::new(file, 0, len, repaired_spooled)),
+            };
+            resolved.hit = Some(Hit {
+                status,
+                start,
+                end,
+                partial_end,
+                data,
+                repaired,
+                spooled,
+                candidates,
+                score,
+                parent,
+                encoding: None,
+                object: None,
+            });
+        }
+        self.quick_clean();
+        resolved
+    }
+
+    /// Create a hit for every outermost nested JSON string that has completed
+    /// within the current JSON string.
+    ///
+    /// `data` holds the bytes of the current JSON string that have not been
+    /// spooled (see [`JsonTracker::data`]), and `lookback` holds the bytes
+    /// that the state machine has consumed most recently, along with the
+    /// position in the stream that they end at.
+    fn salvaged_hits<'t>(
+        &self,
+        data: &Cow<'t, [u8]>,
+        spool: Option<&'t File>,
+        lookback: (&[u8], usize),
+        opts: &Options,
+    ) -> Vec<Hit<'t>> {
+        let mut hits = Vec::with_capacity(self.salvaged.len());
+        for &(start, end, ref features) in &self.salvaged {
+            let size = end - start + 1;
+            let score = opts.min_score.map(|_| {
+                let context = score::context(lookback.0, lookback.1, self.start + start);
+                score::score(features, size, context)
+            });
+            if size < opts.min_size || score < opts.min_score || !opts.keeps_structure(features) {
+                continue;
+            }
+            // The nested JSON string may start within the spooled bytes.
+            let spooled = match spool {
+                Some(file) if start < self.spooled => {
+                    let len = (end + 1).min(self.spooled) - start;
+                    Some(Spooled::new(file, start, len, 0))
+                }
+                _ => None,
+            };
+            let range =
+                start.max(self.spooled) - self.spooled..(end + 1).max(self.spooled) - self.spooled;
+            let data = slice_cow(data, range);
+            hits.push(Hit {
+                status: Status::Salvaged,
+                start: self.start + start,
+                end: self.start + end,
+                partial_end: self.start + end,
+                data,
+                repaired: None,
+                spooled,
+                candidates: vec![],
+                score,
+                parent: None,
+                encoding: None,
+                object: None,
+            });
+        }
+        hits
+    }
+
+    /// Return the position within the current JSON string that the carver
+    /// has to scan again from, once `cause` has stopped it, if any.
+    fn rewind(&self, cause: &Cause, consumed: &[u8]) -> Option<usize> {
+        match (cause, self.corrupted_at) {
+            // Too large JSON strings may contain smaller ones.
+            (Cause::TooLarge, _) => Some(1),
+            // So may any JSON string, if the carver carves exhaustively.
+            (Cause::Completed | Cause::Corrupted | Cause::Exhausted | Cause::TooDeep, _)
+                if self.exhaustive =>
+            {
+                Some(1)
+            }
+            // The bytes that the carver has looked ahead at may contain the
+            // next JSON string.
+            (Cause::Corrupted, Some(at)) => Some(at),
+            // A string literal may have started at a stray quote, and
+            // swallowed the start of an array or object. String literals are
+            // never spooled.
+            (Cause::Completed | Cause::Corrupted | Cause::Exhausted, _) if self.is_literal() => {
+                let data = self.data(consumed);
+                let end = self.corrupted_at.unwrap_or(self.cur);
+                memchr::memchr2(
+                    CHAR_LEFT_SQUARE_BRACKET,
+                    CHAR_LEFT_CURLY_BRACKET,
+                    &data[..end],
+                )
+            }
+            _ => None,
+        }
+    }
+
+    /// Return the bytes of the current JSON string from position `from`
+    /// onwards, so that they can be scanned again.
+    fn replay(&self, from: usize, consumed: &[u8], spool: Option<&File>) -> io::Result<Vec<u8>> {
+        let mut buf = Vec::with_capacity(self.cur);
+        if let (1.., Some(file)) = (self.spooled, spool) {
+            Spooled::new(file, 0, self.spooled, 0).copy_to(self.spooled, &mut buf)?;
+        }
+        buf.extend_from_slice(&self.data(consumed));
+        buf.drain(..from);
+        Ok(buf)
+    }
+
+    /// Create a hit for the JSON string that ends at the last unmatched
+    /// closing bracket, if its suffix can be recovered, and prepare for the
+    /// next one.
+    ///
+    /// `history` holds the bytes that the state machine has consumed most
+    /// recently.
+    fn recover_suffix(&mut self, history: &[u8], opts: &Options) -> Resolved<'static> {
+        let window = self.suffix_window.unwrap_or(0);
+        let lookback = &history[history.len().saturating_sub(window)..];
+        // The position of the lookback bytes in the stream.
+        let offset = self.pos - lookback.len();
+        let end = self.start + self.partial_close_end;
+        let mut resolved = Resolved::default();
+        if let Some(suffix) = end
+            .checked_sub(offset)
+            .and_then(|end| suffix::scan_suffix(lookback, end))
+        {
+            let mut data = lookback[suffix.start..=end - offset].to_vec();
+            if data.len() >= opts.min_size {
+                if opts.replace_newlines {
+                    replace_newlines(&mut data);
+                }
+                let repaired = [&suffix.prefix[..], &data].concat();
+                // The state machine has not parsed the suffix, so it has to
+                // be scanned again for its features.
+                let features = match opts.min_score.is_some() || opts.filters_structure() {
+                    true => self.rescan(&repaired).0.features,
+                    false => Features::default(),
+                };
+                let score = opts.min_score.map(|_| {
+                    let context = score::context(history, self.pos, offset + suffix.start);
+                    score::score(&features, data.len(), context)
+                });
+                // The unmatched closing brackets of a JSON string that is
+                // scanned again belong to it.
+                let nested = self
+                    .outer
+                    .is_some_and(|(outer_start, outer_end)| outer_start < end && end <= outer_end);
+                if score >= opts.min_score
+                    && (!opts.filters_structure() || opts.keeps_structure(&features))
+                    && !nested
+                {
+                    resolved.hit = Some(Hit {
+                        status: Status::SuffixRecovered,
+                        start: offset + suffix.start,
+                        end,
+                        partial_end: end,
+                        data: Cow::Owned(data),
+                        repaired: Some(repaired),
+                        spooled: None,
+                        candidates: vec![],
+                        score,
+                        parent: None,
+                        encoding: None,
+                        object: None,
+                    });
+                }
+            }
+        }
+        self.quick_clean();
+        resolved
+    }
+
+    /// Stop the JSON string that is in progress, if any, because the stream
+    /// has ended. Its bytes must have been spilled already, and `lookback`
+    /// must hold the bytes that the state machine has consumed most recently.
+    ///
+    /// If the carver was looking ahead past the byte that corrupted the JSON
+    /// string, the caller has to scan the bytes after it again.
+    fn exhaust<'t>(
+        &mut self,
+        spool: Option<&'t File>,
+        lookback: &[u8],
+        opts: &Options,
+    ) -> Resolved<'t> {
+        match self.state {
+            State::Scout => Resolved::default(),
+            State::Orphan => self.recover_suffix(lookback, opts),
+            // The carver was looking ahead past the byte that corrupted the
+            // JSON string.
+            _ if self.corrupted_at.is_some() => {
+                self.resolve(Cause::Corrupted, &[], spool, lookback, opts)
+            }
+            _ => self.resolve(Cause::Exhausted, &[], spool, lookback, opts),
+        }
+    }
+
+    /// Feed a buffer to the state machine, until a JSON string is completed
+    /// or corrupted, or until the buffer is consumed.
+    ///
+    /// Return the number of bytes that were consumed, and the cause that
+    /// stopped the JSON string, if any. The byte that corrupts a JSON string
+    /// is not consumed, since it may be the start of the next one.
+    fn feed(&mut self, buf: &[u8]) -> (usize, Option<Cause>) {
+        let mut i = 0;
+        let mut cause = None;
+        while i < buf.len() {
+            // Look ahead past the byte that corrupted the JSON string, without
+            // changing the state.
+            if let (Some(at), Some(lookahead)) = (self.corrupted_at, self.repair_lookahead) {
+                let n = (lookahead - (self.cur - at)).min(buf.len() - i);
+                self.advance_by(n);
+                i += n;
+                if self.cur - at == lookahead {
+                    cause = Some(Cause::Corrupted);
+                    break;
+                }
+                continue;
+            }
+
+            if self.state == State::Scout {
+                let next = match (self.suffix_window, self.min_string_len) {
+                    (None, None) => memchr::memchr2(
+                        CHAR_LEFT_SQUARE_BRACKET,
+                        CHAR_LEFT_CURLY_BRACKET,
+                        &buf[i..],
+                    ),
+                    // Look for the quotes of string literals as well.
+                    (None, Some(_)) => memchr::memchr3(
+                        CHAR_LEFT_SQUARE_BRACKET,
+                        CHAR_LEFT_CURLY_BRACKET,
+                        CHAR_QUOT_MARK,
+                        &buf[i..],
+                    ),
+                    // Look for unmatched closing brackets as well.
+                    (Some(_), strings) => buf[i..].iter().position(|&b| {
+                        matches!(
+                            b,
+                            CHAR_LEFT_SQUARE_BRACKET
+                                | CHAR_LEFT_CURLY_BRACKET
+                                | CHAR_RIGHT_SQUARE_BRACKET
+                                | CHAR_RIGHT_CURLY_BRACKET
+                        ) || (b == CHAR_QUOT_MARK && strings.is_some())
+                    }),
+                };
+                match next {
+                    Some(j)
+                        if buf[i + j] == CHAR_QUOT_MARK && self.pos + i + j < self.quotes_from =>
+                    {
+                        i += j + 1;
+                        continue;
+                    }
+                    Some(j) => {
+                        i += j;
+                        self.start = self.pos + i;
+                    }
+                    None => {
+                        i = buf.len();
+                        break;
+                    }
+                }
+            }
+
+            // Skip through the contents of strings in bulk, since most of
+            // their bytes do not affect the state.
+            if let State::String {
+                closed: false,
+                in_escape: false,
+                in_escaped_unicode: 0,
+            } = self.state
+            {
+                let rest = &buf[i..];
+                let end = memchr::memchr2(CHAR_QUOT_MARK, CHAR_ESCAPE, rest).unwrap_or(rest.len());
+                // Strict carvers validate the non-ASCII bytes one by one. A
+                // high surrogate escape must be followed by another escape,
+                // and a UTF-8 lead byte by continuation bytes.
+                let end = match self.strict {
+                    true if self.in_surrogate_pair || self.utf8_needed > 0 => 0,
+                    true => rest[..end]
+                        .iter()
+                        .position(|&b| byte_needs_escape(b) || !b.is_ascii())
+                        .unwrap_or(end),
+                    false => rest[..end]
+                        .iter()
+                        .position(|&b| byte_needs_escape(b))
+                        .unwrap_or(end),
+                };
+                let end = end.min(self.max_size - self.cur);
+                if self.in_key && self.key_plausible {
+                    self.key_plausible = rest[..end].iter().all(|&b| score::is_key_byte(b));
+                }
+                self.advance_by(end);
+                i += end;
+                if i == buf.len() {
+                    break;
+                }
+            }
+
+            if self.cur == self.max_size && self.in_progress() && self.state != State::Orphan {
+                cause = Some(Cause::TooLarge);
+                break;
+            }
+
+            match self.step(buf[i]) {
+                None => i += 1,
+                Some(Cause::Completed) => {
+                    i += 1;
+                    cause = Some(Cause::Completed);
+                    break;
+                }
+                Some(Cause::Corrupted) if self.repair_lookahead.is_some() => {
+                    self.corrupted_at = Some(self.cur);
+                }
+                Some(c) => {
+                    cause = Some(c);
+                    break;
+                }
+            }
+        }
+        self.pos += i;
+        (i, cause)
+    }
+
+    /// Process a single byte of a JSON string.
+    ///
+    /// Return `None` if the byte was consumed and the JSON string continues,
+    /// or the cause that stopped the JSON string otherwise.
+    fn step(&mut self, b: u8) -> Option<Cause> {
+        let res = match self.state {
+            // The scout has already made sure that this is a bracket, or a
+            // quote, if string literals are carved.
+            State::Scout => match b {
+                CHAR_RIGHT_SQUARE_BRACKET | CHAR_RIGHT_CURLY_BRACKET => {
+                    self.state = State::Orphan;
+                    self.partial_close_end = self.cur;
+                    self.skip()
+                }
+                _ => Some(Cause::Found(b)),
+            },
+            State::Orphan => match b {
+                CHAR_RIGHT_SQUARE_BRACKET | CHAR_RIGHT_CURLY_BRACKET => {
+                    self.partial_close_end = self.cur;
+                    self.skip()
+                }
+                CHAR_SPACE | CHAR_TAB | CHAR_NEWLINE | CHAR_CARRIAGE_RETURN => self.skip(),
+                CHAR_COMMA => Some(Cause::Continued),
+                _ => Some(Cause::Suffix),
+            },
+            State::ArrayStart => match b {
+                CHAR_LEFT_SQUARE_BRACKET
+                | CHAR_LEFT_CURLY_BRACKET
+                | CHAR_RIGHT_SQUARE_BRACKET
+                | CHAR_QUOT_MARK
+                | CHAR_MINUS
+                | CHAR_ZERO..=CHAR_NINE
+                | CHAR_START_FALSE
+                | CHAR_START_NULL
+                | CHAR_START_TRUE => Some(Cause::Found(b)),
+                CHAR_SPACE | CHAR_TAB | CHAR_NEWLINE | CHAR_CARRIAGE_RETURN => self.skip(),
+                _ => Some(Cause::Corrupted),
+            },
+            State::ObjectStart => match b {
+                CHAR_QUOT_MARK => {
+                    self.in_key = true;
+                    Some(Cause::Found(b))
+                }
+                CHAR_RIGHT_CURLY_BRACKET => Some(Cause::Found(b)),
+                CHAR_SPACE | CHAR_TAB | CHAR_NEWLINE | CHAR_CARRIAGE_RETURN => self.skip(),
+                _ => Some(Cause::Corrupted),
+            },
+            State::ValueEnd => match b {
+                CHAR_COMMA | CHAR_RIGHT_SQUARE_BRACKET | CHAR_RIGHT_CURLY_BRACKET => {
+                    Some(Cause::Found(b))
+                }
+                CHAR_SPACE | CHAR_TAB | CHAR_NEWLINE | CHAR_CARRIAGE_RETURN => self.skip(),
+                _ => Some(Cause::Corrupted),
+            },
+            State::Colon | State::ArrayComma => match b {
+                CHAR_LEFT_CURLY_BRACKET
+                | CHAR_LEFT_SQUARE_BRACKET
+                | CHAR_MINUS
+                | CHAR_ZERO..=CHAR_NINE
+                | CHAR_QUOT_MARK
+                | CHAR_START_FALSE
+                | CHAR_START_NULL
+                | CHAR_START_TRUE => Some(Cause::Found(b)),
+                CHAR_SPACE | CHAR_TAB | CHAR_NEWLINE | CHAR_CARRIAGE_RETURN => self.skip(),
+                _ => Some(Cause::Corrupted),
+            },
+            State::ObjectComma => match b {
+                CHAR_QUOT_MARK => {
+                    self.in_key = true;
+                    Some(Cause::Found(b))
+                }
+                CHAR_SPACE | CHAR_TAB | CHAR_NEWLINE | CHAR_CARRIAGE_RETURN => self.skip(),
+                _ => Some(Cause::Corrupted),
+            },
+            State::String { .. } => self.step_string(b),
+            State::Number { .. } => self.step_number(b),
+            State::Literal(literal) => {
+                if literal[0] != b {
+                    return Some(Cause::Corrupted);
+                }
+                self.advance();
+                self.state = match literal.len() {
+                    1 => State::ValueEnd,
+                    _ => State::Literal(&literal[1..]),
+                };
+                if self.state == State::ValueEnd {
+                    self.mark_value_end();
+                }
+                None
+            }
+        };
+
+        match res {
+            Some(Cause::Found(b)) => self.found(b),
+            res => res,
+        }
+    }
+
+    /// Consume a byte that does not change the state, e.g., whitespace.
+    fn skip(&mut self) -> Option<Cause> {
+        self.advance();
+        None
+    }
+
+    /// Process a byte within a string.
+    fn step_string(&mut self, b: u8) -> Option<Cause> {
+        let State::String {
+            mut closed,
+            mut in_escape,
+            mut in_escaped_unicode,
+        } = self.state
+        else {
+            unreachable!()
+        };
+        if closed {
+            // We're at least one indentation level deep when a string is
+            // followed by something, so we can safely unwrap().
+            let last_ident = self.last_ident().unwrap();
+            return match (b, last_ident, self.in_key) {
+                (CHAR_SPACE | CHAR_TAB | CHAR_NEWLINE | CHAR_CARRIAGE_RETURN, _, _) => self.skip(),
+                // Case 1: A string value in a JSON list: ["test", "1"]
+                (CHAR_COMMA | CHAR_RIGHT_SQUARE_BRACKET, CHAR_LEFT_SQUARE_BRACKET, _) => {
+                    Some(Cause::Found(b))
+                }
+                // Case 2: A value in a JSON object: {"test": "yes", "pain": "right"}
+                (CHAR_COMMA | CHAR_RIGHT_CURLY_BRACKET, CHAR_LEFT_CURLY_BRACKET, false) => {
+                    Some(Cause::Found(b))
+                }
+                // Case 3: A key in a JSON object: {"test": 1, "pain": true}
+                (CHAR_COLON, CHAR_LEFT_CURLY_BRACKET, true) => Some(Cause::Found(b)),
+                (_, _, _) => Some(Cause::Corrupted),
+            };
+        }
+
+        if self.strict && !self.step_unicode(b, in_escape, in_escaped_unicode) {
+            if self.corrupted_at.is_none() {
+                self.invalid_unicode = true;
+            }
+            return Some(Cause::Corrupted);
+        }
+
+        match (b, in_escape, in_escaped_unicode) {
+            (CHAR_ESCAPE, false, 0) => {
+                in_escape = true;
+                self.key_plausible = false;
+            }
+            (CHAR_QUOT_MARK, false, 0) => {
+                closed = true;
+                let len = self.cur - self.string_start - 1;
+                if self.in_key && self.key_plausible && score::is_plausible_key(len) {
+                    self.features.plausible_keys += 1;
+                }
+            }
+            (0x00..=0x1F, _, _) => return Some(Cause::Corrupted),
+            (_, false, 0) => {
+                if byte_needs_escape(b) {
+                    return Some(Cause::Corrupted);
+                }
+                if !score::is_key_byte(b) {
+                    self.key_plausible = false;
+                }
+            }
+            (CHAR_U, true, 0) => {
+                in_escaped_unicode = 4;
+                in_escape = false;
+            }
+            (_, true, 0) => {
+                if byte_can_escape(b) {
+                    in_escape = false;
+                } else {
+                    return Some(Cause::Corrupted);
+                }
+            }
+            (_, _, 1..=4) => {
+                if b.is_ascii_hexdigit() {
+                    in_escaped_unicode -= 1;
+                } else {
+                    return Some(Cause::Corrupted);
+                }
+            }
+            (_, _, _) => return Some(Cause::Corrupted),
+        }
+        self.advance();
+        self.state = State::String {
+            closed,
+            in_escape,
+            in_escaped_unicode,
+        };
+        if closed && !self.in_key {
+            self.mark_value_end();
+        }
+        // A string literal outside of any array or object ends with its
+        // closing quote.
+        match closed && self.ident_levels.is_empty() {
+            true => Some(Cause::Completed),
+            false => None,
+        }
+    }
+
+    /// Check that a byte within a string keeps it valid Unicode, i.e., that
+    /// it does not break a UTF-8 character or a surrogate pair escape.
+    ///
+    /// Bytes that are not allowed by the JSON grammar are left to
+    /// [`Self::step_string`].
+    fn step_unicode(&mut self, b: u8, in_escape: bool, in_escaped_unicode: u8) -> bool {
+        if self.utf8_needed > 0 {
+            let (low, high) = self.utf8_range;
+            if !(low..=high).contains(&b) {
+                return false;
+            }
+            self.utf8_needed -= 1;
+            self.utf8_range = UTF8_CONTINUATION;
+            return true;
+        }
+        match (in_escape, in_escaped_unicode) {
+            (false, 0) if self.in_surrogate_pair => b == CHAR_ESCAPE,
+            (false, 0) => match utf8_lead(b) {
+                Some((needed, range)) => {
+                    self.utf8_needed = needed;
+                    self.utf8_range = range;
+                    true
+                }
+                None => false,
+            },
+            (true, 0) if self.in_surrogate_pair => b == CHAR_U,
+            (_, 1..=4) => {
+                let Some(digit) = (b as char).to_digit(16) else {
+                    return true;
+                };
+                self.escaped_unit = self.escaped_unit << 4 | digit as u16;
+                if in_escaped_unicode > 1 {
+                    return true;
+                }
+                let unit = std::mem::take(&mut self.escaped_unit);
+                match (self.in_surrogate_pair, unit) {
+                    (false, 0xD800..=0xDBFF) => self.in_surrogate_pair = true,
+                    (true, 0xDC00..=0xDFFF) => self.in_surrogate_pair = false,
+                    (true, _) | (false, 0xDC00..=0xDFFF) => return false,
+                    (false, _) => (),
+                }
+                true
+            }
+            _ => true,
+        }
+    }
+
+    /// Process a byte within a number.
+    fn step_number(&mut self, b: u8) -> Option<Cause> {
+        let State::Number {
+            prev,
+            mut in_frac,
+            mut in_exp,
+            mut in_leading_zero,
+        } = self.state
+        else {
+            unreachable!()
+        };
+
+        // Check for leading zeroes.
+        //
+        // A leading zero can be preceeded by a minus sign (-), but cannot
+        // be followed by digits.
+        if in_leading_zero.is_none() {
+            in_leading_zero = match prev {
+                CHAR_MINUS => None,
+                CHAR_ZERO => Some(true),
+                _ => Some(false),
+            }
+        }
+        if in_leading_zero == Some(true) {
+            in_leading_zero = match b {
+                CHAR_ZERO..=CHAR_NINE => return Some(Cause::Corrupted),
+                _ => Some(false),
+            }
+        }
+
+        match (prev, b) {
+            // Only numbers can follow +/-/..
+            (CHAR_MINUS | CHAR_PLUS | CHAR_DECIMAL, CHAR_ZERO..=CHAR_NINE) => (),
+            // Only numbers or +/- can follow exponent signs.
+            (CHAR_EXP_LOWER | CHAR_EXP_UPPER, CHAR_ZERO..=CHAR_NINE | CHAR_MINUS | CHAR_PLUS) => (),
+            // Digits, insignificant whitespace, or ,]} can *always* follow
+            // digits.
+            (
+                CHAR_ZERO..=CHAR_NINE,
+                CHAR_ZERO..=CHAR_NINE | CHAR_SPACE | CHAR_TAB | CHAR_NEWLINE,
+            ) => {
+                if !b.is_ascii_digit() {
+                    self.mark_value_end();
+                }
+            }
+            // Decimal points can follow numbers if we're not in a
+            // fractional/exponent part already.
+            (CHAR_ZERO..=CHAR_NINE, CHAR_DECIMAL) => match (in_frac, in_exp) {
+                (true, _) | (_, true) => return Some(Cause::Corrupted),
+                (false, _) => in_frac = true,
+            },
+            // Exponent signs can follow numbers if we're not in a exponent
+            // part already.
+            (CHAR_ZERO..=CHAR_NINE, CHAR_EXP_LOWER | CHAR_EXP_UPPER) => match in_exp {
+                true => return Some(Cause::Corrupted),
+                false => in_exp = true,
+            },
+            // Numbers are complete only if digits and insignificant
+            // whitespace are followed by ,]}.
+            (
+                CHAR_SPACE | CHAR_TAB | CHAR_NEWLINE | CHAR_CARRIAGE_RETURN | CHAR_ZERO..=CHAR_NINE,
+                CHAR_COMMA | CHAR_RIGHT_SQUARE_BRACKET | CHAR_RIGHT_CURLY_BRACKET,
+            ) => {
+                if prev.is_ascii_digit() {
+                    self.mark_value_end();
+                }
+                return Some(Cause::Found(b));
+            }
+            // Everything else is not permitted.
+            (_, _) => return Some(Cause::Corrupted),
+        }
+        self.advance();
+        self.state = State::Number {
+            prev: b,
+            in_frac,
+            in_exp,
+            in_leading_zero,
+        };
+        None
+    }
+
+    /// Enter the state that a structural character or the first character
+    /// of a value leads to. The character is expected to be valid at this
+    /// point.
+    fn found(&mut self, b: u8) -> Option<Cause> {
+        self.state = match b {
+            CHAR_LEFT_SQUARE_BRACKET | CHAR_LEFT_CURLY_BRACKET => {
+                // The outermost bracket is the JSON string itself.
+                if !self.ident_levels.is_empty() {
+                    self.features.values += 1;
+                }
+                self.add_array_member();
+                if self.add_ident(b).is_err() {
+                    return Some(Cause::TooDeep);
+                }
+                match b {
+                    CHAR_LEFT_SQUARE_BRACKET => State::ArrayStart,
+                    _ => State::ObjectStart,
+                }
+            }
+            CHAR_RIGHT_SQUARE_BRACKET | CHAR_RIGHT_CURLY_BRACKET => {
+                let expected = match b {
+                    CHAR_RIGHT_SQUARE_BRACKET => CHAR_LEFT_SQUARE_BRACKET,
+                    _ => CHAR_LEFT_CURLY_BRACKET,
+                };
+                match self.remove_ident(expected) {
+                    Ok(true) => return Some(Cause::Completed),
+                    Ok(false) => State::ValueEnd,
+                    Err(_) => return Some(Cause::Corrupted),
+                }
+            }
+            CHAR_COLON => {
+                self.in_key = false;
+                self.advance();
+                State::Colon
+            }
+            CHAR_COMMA => {
+                self.advance();
+                match self.last_ident() {
+                    Some(CHAR_LEFT_SQUARE_BRACKET) => State::ArrayComma,
+                    Some(CHAR_LEFT_CURLY_BRACKET) => State::ObjectComma,
+                    Some(_) => unreachable!(), // FIXME: Ensure that this is indeed unreachable.
+                    None => unreachable!(),    // FIXME: Ensure that this is indeed unreachable.
+                }
+            }
+            CHAR_QUOT_MARK => {
+                match self.in_key {
+                    true => {
+                        self.features.keys += 1;
+                        self.key_plausible = true;
+                        self.add_member();
+                    }
+                    false => {
+                        self.features.values += 1;
+                        self.features.strings += 1;
+                        self.add_array_member();
+                    }
+                }
+                self.string_start = self.cur;
+                self.advance();
+                State::String {
+                    closed: false,
+                    in_escape: false,
+                    in_escaped_unicode: 0,
+                }
+            }
+            CHAR_MINUS | CHAR_ZERO..=CHAR_NINE => {
+                self.features.values += 1;
+                self.features.numbers += 1;
+                self.add_array_member();
+                self.advance();
+                State::Number {
+                    prev: b,
+                    in_frac: false,
+                    in_exp: false,
+                    in_leading_zero: None,
+                }
+            }
+            CHAR_START_FALSE | CHAR_START_NULL | CHAR_START_TRUE => {
+                self.features.values += 1;
+                self.add_array_member();
+                self.advance();
+                State::Literal(match b {
+                    CHAR_START_FALSE => b"alse",
+                    CHAR_START_NULL => b"ull",
+                    _ => b"rue",
+                })
+            }
+            // The states only hand over the bytes that they expect, but if
+            // that ever breaks, the JSON string is corrupted, instead of the
+            // carver.
+            _ => {
+                debug_assert!(false, "unexpected byte {b:#04x}");
+                return Some(Cause::Corrupted);
+            }
+        };
+        None
     }
 }
 
 /// Implementation of a stream reader.
+///
+/// This is a convenience type for the most common sources of data. The
+/// [`Carver`] accepts any type that implements [`BufRead`].
 pub enum Reader<'a> {
     /// A file reader
     File(BufReader<File>),
@@ -352,6 +1917,8 @@ pub enum Reader<'a> {
     Stdin(StdinLock<'a>),
     /// A local buffer reader
     Local(BufReader<&'a [u8]>),
+    /// A memory-mapped file reader
+    Mmap(io::Cursor<Mmap>),
 }
 
 impl<'a> Reader<'a> {
@@ -368,6 +1935,21 @@ impl<'a> Reader<'a> {
         Reader::Stdin(io::stdin().lock())
     }
 
+    /// Create a Reader that maps a file to memory.
+    ///
+    /// The whole file is exposed as a single buffer, so the carver can scan
+    /// it without copying it around, which makes a difference for large
+    /// dumps.
+    #[allow(unsafe_code)]
+    pub fn from_mmap(file: &File) -> io::Result<Reader<'a>> {
+        // SAFETY: The mapping is read-only, and we expect that the file is
+        // not modified while we carve it. If it is, the carver may produce
+        // garbage, but this is no different from reading a file that
+        // changes underneath us.
+        let mmap = unsafe { Mmap::map(file)? };
+        Ok(Reader::Mmap(io::Cursor::new(mmap)))
+    }
+
     fn mut_ref(&mut self) -> &mut dyn BufRead {
         // Some type voodo are involved:
         // https://users.rust-lang.org/t/why-ref-mut-and-not-mut-in-enum-matching/95721/8
@@ -375,11 +1957,31 @@ impl<'a> Reader<'a> {
             Reader::File(r) => r,
             Reader::Stdin(r) => r,
             Reader::Local(r) => r,
+            Reader::Mmap(r) => r,
         }
     }
 }
 
+impl Read for Reader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.mut_ref().read(buf)
+    }
+}
+
+impl BufRead for Reader<'_> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.mut_ref().fill_buf()
+    }
+
+    fn consume(&mut self, amount: usize) {
+        self.mut_ref().consume(amount)
+    }
+}
+
 /// Implementation of a stream writer.
+///
+/// This is a convenience type for the most common sinks of data. The
+/// [`Carver`] accepts any type that implements [`Write`].
 pub enum Writer<'a> {
     /// A file writer
     File(BufWriter<File>),
@@ -422,567 +2024,643 @@ impl<'a> Writer<'a> {
     }
 }
 
+impl Write for Writer<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.mut_ref().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.mut_ref().flush()
+    }
+}
+
+// The options of a Carver that affect how hits are reported.
+#[derive(Clone, Copy, Debug)]
+struct Options {
+    min_size: usize,
+    fix_incomplete: bool,
+    repair_mode: RepairMode,
+    replace_newlines: bool,
+    spool_threshold: Option<usize>,
+    page_size: Option<usize>,
+    min_score: Option<u8>,
+    min_depth: usize,
+    min_members: usize,
+    top_level: Option<TopLevel>,
+}
+
+impl Options {
+    /// Whether the JSON strings are filtered by their structure.
+    fn filters_structure(&self) -> bool {
+        self.min_depth > 1 || self.min_members > 0 || self.top_level.is_some()
+    }
+
+    /// Whether a JSON string with these features has the structure that the
+    /// JSON strings should have.
+    fn keeps_structure(&self, features: &Features) -> bool {
+        let top_level = match self.top_level {
+            None => true,
+            Some(TopLevel::Object) => features.object,
+            Some(TopLevel::Array) => !features.object,
+        };
+        top_level && features.depth >= self.min_depth && features.members >= self.min_members
+    }
+}
+
 /// The Carver struct is responsible for carving JSON strings out of the
-/// provided reader, and provide output and reports to the provided writers.
-pub struct Carver<'a> {
+/// provided reader. The carved strings can be retrieved one by one through
+/// [`Carver::carve`], or be passed to a [`Sink`] through [`Carver::parse`].
+///
+/// The reader can be any type that implements [`BufRead`], e.g., a
+/// [`Reader`], a `&[u8]` buffer, or an `io::Cursor`. Alternatively, the
+/// Carver can be fed with chunks of data through [`Carver::feed`], in which
+/// case a reader is not necessary (see [`Carver::incremental`]).
+pub struct Carver<R> {
     jt: JsonTracker,
-    reader: Reader<'a>,
-    json_writer: Writer<'a>,
-    report_writer: Writer<'a>,
+    reader: R,
+    // Whether the reader has been exhausted.
+    done: bool,
+    spool: Spool,
+    // The bytes that need to be scanned again, because the JSON string that
+    // they were part of was too large, or because the carver has looked ahead
+    // at them.
+    replay: Vec<u8>,
+    replay_pos: usize,
+    // The hits that have been carved but not returned yet, since a JSON
+    // string may produce more than one.
+    pending: VecDeque<Hit<'static>>,
+    // The bytes that have been consumed most recently, if the carver
+    // recovers suffixes or scores JSON strings.
+    history: Vec<u8>,
+    replace_newlines: bool,
+    page_size: Option<usize>,
     /// The minimum size of the JSON string that will be reported.
     pub min_size: usize,
     /// Whether to attempt to fix incomplete JSON strings.
     pub fix_incomplete: bool,
-    /// Whether to report every detected JSON, not just corrupted ones.
+    /// How to fix incomplete JSON strings.
+    pub repair_mode: RepairMode,
+    /// Move the bytes of a JSON string to a temporary file, once this many of
+    /// them have to be kept in memory. This bounds the memory that the
+    /// carver needs for JSON strings that do not fit in the buffer of the
+    /// reader (see [`Hit::spooled`]).
+    pub spool_threshold: Option<usize>,
+    /// The minimum score of the JSON strings that will be reported, from 0
+    /// to 100 (see [`Hit::score`]). If not set, JSON strings are not scored.
+    ///
+    /// The score is based on the nesting depth of the JSON string, its
+    /// number of values, how many of its keys look like names, how many of
+    /// its values are strings rather than numbers, its size, and the entropy
+    /// of the bytes right before it.
+    pub min_score: Option<u8>,
+    /// The minimum nesting depth of the JSON strings that will be reported.
+    /// A JSON string without nested arrays or objects, e.g., `[1, 2]`, is one
+    /// level deep.
+    pub min_depth: usize,
+    /// The minimum number of keys of the objects, or of elements of the
+    /// arrays, that will be reported. Only the outermost object or array of a
+    /// JSON string counts.
+    pub min_members: usize,
+    /// The type of the JSON strings that will be reported. If not set, both
+    /// objects and arrays are reported.
+    pub top_level: Option<TopLevel>,
+    /// Whether [`Carver::parse_writers`] reports every JSON string, not just
+    /// corrupted ones.
     pub report_all: bool,
 }
 
-impl<'a> Carver<'a> {
-    /// Create a new `Carver` instance from the provided `Reader` and `Writer`
-    /// instances.
-    pub fn new(
-        reader: Reader<'a>,
-        json_writer: Writer<'a>,
-        report_writer: Writer<'a>,
-        max_size: Option<usize>,
-        max_ident_depth: Option<usize>,
-    ) -> Self {
+/// An iterator over the JSON strings that a [`Carver`] finds in its reader.
+///
+/// Created by [`Carver::carve`]. Since the iterator cannot lend the buffer of
+/// the reader, the hits own their bytes. Use [`Carver::parse`] to avoid
+/// copying them.
+pub struct Carve<'c, R> {
+    carver: &'c mut Carver<R>,
+}
+
+impl<R: BufRead> Iterator for Carve<'_, R> {
+    type Item = Result<Hit<'static>, errors::Err>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.carver.next_hit().transpose()
+    }
+}
+
+/// A writer that feeds every byte written to it to a [`Carver`], and passes
+/// the carved JSON strings to a [`Sink`].
+///
+/// Created by [`Carver::feed_writer`]. Call [`FeedWriter::finish`] once
+/// the stream has ended, so that the last JSON string is reported as well.
+pub struct FeedWriter<'c, R, S> {
+    carver: &'c mut Carver<R>,
+    sink: S,
+}
+
+impl<R, S: Sink> FeedWriter<'_, R, S> {
+    /// Report the JSON string that the stream ended on, if any, and return
+    /// the sink.
+    pub fn finish(mut self) -> io::Result<S> {
+        self.carver.finish(&mut self.sink)?;
+        Ok(self.sink)
+    }
+}
+
+impl<R, S: Sink> Write for FeedWriter<'_, R, S> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.carver.feed(buf, &mut self.sink)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Carver<io::Empty> {
+    /// Create a new `Carver` instance without a reader. Data can be pushed
+    /// to it incrementally through [`Carver::feed`].
+    pub fn incremental(buf_size: Option<usize>, max_ident_depth: Option<usize>) -> Self {
+        Carver::new(io::empty(), buf_size, max_ident_depth)
+    }
+}
+
+impl<R> Carver<R> {
+    /// Create a new `Carver` instance from the provided reader.
+    ///
+    /// `buf_size` is the initial size of the buffer for JSON strings that
+    /// span multiple reads (empty by default, it grows on demand), and
+    /// `max_ident_depth` is the maximum identation
+    /// depth of the JSON strings (see [`Carver::max_depth`]).
+    pub fn new(reader: R, buf_size: Option<usize>, max_ident_depth: Option<usize>) -> Self {
         Carver {
-            jt: JsonTracker::new(max_size, max_ident_depth),
-            reader: reader,
-            json_writer: json_writer,
-            report_writer: report_writer,
+            jt: JsonTracker::new(buf_size, max_ident_depth),
+            reader,
+            done: false,
+            spool: Spool::default(),
+            replay: vec![],
+            replay_pos: 0,
+            pending: VecDeque::new(),
+            history: vec![],
+            replace_newlines: false,
+            page_size: None,
             min_size: DEFAULT_MIN_JSON_SIZE,
             fix_incomplete: false,
+            repair_mode: RepairMode::default(),
+            spool_threshold: None,
+            min_score: None,
+            min_depth: 1,
+            min_members: 0,
+            top_level: None,
             report_all: false,
         }
     }
 
+    /// Return a reference to the underlying reader.
+    pub fn get_ref(&self) -> &R {
+        &self.reader
+    }
+
     /// Configure whether to replace newlines in JSON strings or not.
     pub fn replace_newlines(&mut self, opt: bool) {
-        self.jt.replace_newlines = opt;
+        self.replace_newlines = opt;
     }
 
-    /// Basically skip_until(), if it could search for two bytes instead of
-    /// one. Here, we mimic its behavior, using the memchr crate, since the
-    /// internal memchr is not stable yet.
-    ///
-    /// The end product of this method is that the next read from the buffer
-    /// should return the character we looked for.
-    fn scout(&mut self) -> Result<Option<(usize, u8)>, io::Error> {
-        let mut read = 0;
-        let mut ch = 0;
-        let r = self.reader.mut_ref();
-        loop {
-            let (done, used) = {
-                let available = match r.fill_buf() {
-                    Ok(n) => n,
-                    Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
-                    Err(e) => return Err(e),
-                };
-                match memchr::memchr2(CHAR_LEFT_SQUARE_BRACKET, CHAR_LEFT_CURLY_BRACKET, available)
-                {
-                    Some(i) => {
-                        // The only difference from skip_until is that we want
-                        // to retain the last character.
-                        ch = available[i];
-                        (true, i + 1)
-                    }
-                    None => (false, available.len()),
-                }
-            };
-            r.consume(used);
-            read += used;
-            if done {
-                return Ok(Some((read, ch)));
-            }
-            if used == 0 {
-                return Ok(None);
-            }
-        }
+    /// Configure whether to salvage the JSON strings that are nested within a
+    /// JSON string that did not complete. The outermost ones that have
+    /// completed are reported as [`Status::Salvaged`], right after the JSON
+    /// string that contains them.
+    pub fn salvage(&mut self, opt: bool) {
+        self.jt.salvage = opt;
     }
 
-    fn handle_left_square_bracket(&mut self) -> Result<Cause, io::Error> {
-        self.jt.add_ident(CHAR_LEFT_SQUARE_BRACKET);
-        for b in self.reader.mut_ref().bytes() {
-            let b = b?;
-            match b {
-                CHAR_LEFT_SQUARE_BRACKET
-                | CHAR_LEFT_CURLY_BRACKET
-                | CHAR_RIGHT_SQUARE_BRACKET
-                | CHAR_QUOT_MARK
-                | CHAR_MINUS
-                | CHAR_ZERO..=CHAR_NINE
-                | CHAR_START_FALSE
-                | CHAR_START_NULL
-                | CHAR_START_TRUE => return Ok(Cause::Found(b)),
-                CHAR_SPACE | CHAR_TAB | CHAR_NEWLINE | CHAR_CARRIAGE_RETURN => self.jt.advance(b),
-                _ => return Ok(Cause::Corrupted(b)),
-            }
-        }
-        Ok(Cause::Exhausted)
+    /// Configure whether to carve exhaustively, i.e., to scan every JSON
+    /// string again, right after its first byte, so that every position of
+    /// the stream is a candidate start of a JSON string. The structurally
+    /// valid JSON strings that start within another JSON string are reported
+    /// as well, along with the position of the outermost one (see
+    /// [`Hit::parent`]). This makes salvaging redundant.
+    pub fn exhaustive(&mut self, opt: bool) {
+        self.jt.exhaustive = opt;
     }
 
-    fn handle_left_curly_bracket(&mut self) -> Result<Cause, io::Error> {
-        self.jt.add_ident(CHAR_LEFT_CURLY_BRACKET);
-        for b in self.reader.mut_ref().bytes() {
-            let b = b?;
-            match b {
-                CHAR_QUOT_MARK => {
-                    self.jt.in_key = true;
-                    return Ok(Cause::Found(b));
-                }
-                CHAR_RIGHT_CURLY_BRACKET => return Ok(Cause::Found(b)),
-                CHAR_SPACE | CHAR_TAB | CHAR_NEWLINE | CHAR_CARRIAGE_RETURN => {
-                    self.jt.advance(b);
-                }
-                _ => return Ok(Cause::Corrupted(b)),
-            }
-        }
-        Ok(Cause::Exhausted)
+    /// Configure whether to carve the string literals that are outside of
+    /// any array or object as well, e.g., `"hello"`, if they have at least
+    /// `min_len` characters once decoded. They are reported as
+    /// [`Status::String`], if their escape sequences and UTF-8 are valid.
+    /// String literals are never spooled, since they have to be decoded.
+    pub fn strings(&mut self, min_len: Option<usize>) {
+        self.jt.min_string_len = min_len;
     }
 
-    fn handle_right_square_bracket(&mut self) -> Result<Cause, io::Error> {
-        match self.jt.remove_ident(CHAR_LEFT_SQUARE_BRACKET) {
-            Ok(true) => return Ok(Cause::Completed),
-            Ok(false) => (),
-            Err(_) => return Ok(Cause::Corrupted(CHAR_RIGHT_SQUARE_BRACKET)),
-        }
+    /// Configure whether to carve strictly, as per RFC 8259, i.e., whether
+    /// strings must be valid UTF-8, without overlong encodings or encoded
+    /// surrogates, and whether their high and low surrogate escapes, e.g.,
+    /// `\uD83D\uDE00`, must be paired. The JSON strings that break these
+    /// rules are reported as [`Status::InvalidUnicode`].
+    pub fn strict(&mut self, opt: bool) {
+        self.jt.strict = opt;
+    }
 
-        for b in self.reader.mut_ref().bytes() {
-            let b = b?;
-            match b {
-                CHAR_COMMA | CHAR_RIGHT_SQUARE_BRACKET | CHAR_RIGHT_CURLY_BRACKET => {
-                    return Ok(Cause::Found(b))
-                }
-                CHAR_SPACE | CHAR_TAB | CHAR_NEWLINE | CHAR_CARRIAGE_RETURN => self.jt.advance(b),
-                _ => return Ok(Cause::Corrupted(b)),
-            }
-        }
-        Ok(Cause::Exhausted)
+    /// Configure whether to recover the ends of JSON strings whose beginning
+    /// was overwritten, by looking back up to `window` bytes from their
+    /// unmatched closing brackets (see [`DEFAULT_SUFFIX_WINDOW`]). They are
+    /// reported as [`Status::SuffixRecovered`].
+    pub fn recover_suffixes(&mut self, window: Option<usize>) {
+        self.jt.suffix_window = window;
     }
 
-    fn handle_right_curly_bracket(&mut self) -> Result<Cause, io::Error> {
-        match self.jt.remove_ident(CHAR_LEFT_CURLY_BRACKET) {
-            Ok(true) => return Ok(Cause::Completed),
-            Ok(false) => (),
-            Err(_) => return Ok(Cause::Corrupted(CHAR_RIGHT_CURLY_BRACKET)),
-        }
+    /// Configure whether to rank the ways in which the JSON strings that did
+    /// not complete can be repaired (see [`Hit::candidates`]). The carver
+    /// looks ahead up to `lookahead` bytes past the byte that corrupted a
+    /// JSON string, so that it can skip corrupted bytes (see
+    /// [`DEFAULT_REPAIR_LOOKAHEAD`]), and then scans them again.
+    pub fn rank_repairs(&mut self, lookahead: Option<usize>) {
+        self.jt.repair_lookahead = lookahead;
+    }
 
-        for b in self.reader.mut_ref().bytes() {
-            let b = b?;
-            match b {
-                CHAR_COMMA | CHAR_RIGHT_SQUARE_BRACKET | CHAR_RIGHT_CURLY_BRACKET => {
-                    return Ok(Cause::Found(b))
-                }
-                CHAR_SPACE | CHAR_TAB | CHAR_NEWLINE | CHAR_CARRIAGE_RETURN => self.jt.advance(b),
-                _ => return Ok(Cause::Corrupted(b)),
-            }
-        }
-        Ok(Cause::Exhausted)
+    /// Configure whether the byte stream is a memory image with pages of
+    /// `size` bytes. JSON strings that are interrupted by the first byte of a
+    /// page are reported as [`Status::PageBoundary`] (see also
+    /// [`Hit::page_boundaries`] and [`PageSearch`]).
+    pub fn page_size(&mut self, size: Option<usize>) {
+        // A page is at least one byte long.
+        self.page_size = size.map(|size| size.max(1));
     }
 
-    fn handle_colon(&mut self) -> Result<Cause, io::Error> {
-        self.jt.in_key = false;
-        self.jt.advance(CHAR_COLON);
-        for b in self.reader.mut_ref().bytes() {
-            let b = b?;
-            match b {
-                CHAR_LEFT_CURLY_BRACKET
-                | CHAR_LEFT_SQUARE_BRACKET
-                | CHAR_MINUS
-                | CHAR_ZERO..=CHAR_NINE
-                | CHAR_QUOT_MARK
-                | CHAR_START_FALSE
-                | CHAR_START_NULL
-                | CHAR_START_TRUE => return Ok(Cause::Found(b)),
-                CHAR_SPACE | CHAR_TAB | CHAR_NEWLINE | CHAR_CARRIAGE_RETURN => self.jt.advance(b),
-                _ => return Ok(Cause::Corrupted(b)),
-            }
-        }
-        Ok(Cause::Exhausted)
-    }
-
-    fn handle_comma(&mut self) -> Result<Cause, io::Error> {
-        self.jt.advance(CHAR_COMMA);
-        match self.jt.last_ident() {
-            Some(CHAR_LEFT_SQUARE_BRACKET) => {
-                for b in self.reader.mut_ref().bytes() {
-                    let b = b?;
-                    match b {
-                        CHAR_LEFT_CURLY_BRACKET
-                        | CHAR_LEFT_SQUARE_BRACKET
-                        | CHAR_MINUS
-                        | CHAR_ZERO..=CHAR_NINE
-                        | CHAR_QUOT_MARK
-                        | CHAR_START_FALSE
-                        | CHAR_START_NULL
-                        | CHAR_START_TRUE => return Ok(Cause::Found(b)),
-                        CHAR_SPACE | CHAR_TAB | CHAR_NEWLINE | CHAR_CARRIAGE_RETURN => {
-                            self.jt.advance(b)
-                        }
-                        _ => return Ok(Cause::Corrupted(b)),
-                    }
-                }
-                Ok(Cause::Exhausted)
-            }
-            Some(CHAR_LEFT_CURLY_BRACKET) => {
-                for b in self.reader.mut_ref().bytes() {
-                    let b = b?;
-                    match b {
-                        CHAR_QUOT_MARK => {
-                            self.jt.in_key = true;
-                            return Ok(Cause::Found(b));
-                        }
-                        CHAR_SPACE | CHAR_TAB | CHAR_NEWLINE | CHAR_CARRIAGE_RETURN => {
-                            self.jt.advance(b)
-                        }
-                        _ => return Ok(Cause::Corrupted(b)),
-                    }
-                }
-                Ok(Cause::Exhausted)
-            }
-            Some(_) => unreachable!(), // FIXME: Ensure that this is indeed unreachable.
-            None => unreachable!(),    // FIXME: Ensure that this is indeed unreachable.
+    /// Configure the maximum identation depth of the JSON strings. JSON
+    /// strings that are nested deeper than that are reported as
+    /// [`Status::TooDeep`]. This is the same as the `max_ident_depth`
+    /// argument of [`Carver::new`].
+    pub fn max_depth(&mut self, depth: usize) {
+        self.jt.max_ident_depth = depth.max(1);
+    }
+
+    /// Configure the maximum size of the JSON strings, in bytes. JSON strings
+    /// that grow larger than that are reported as [`Status::TooLarge`], and
+    /// the carver scans them again, right after their first byte.
+    pub fn max_size(&mut self, size: Option<usize>) {
+        // A JSON string is at least one byte long.
+        self.jt.max_size = size.map_or(usize::MAX, |size| size.max(1));
+    }
+
+    fn options(&self) -> Options {
+        Options {
+            min_size: self.min_size,
+            fix_incomplete: self.fix_incomplete,
+            repair_mode: self.repair_mode,
+            replace_newlines: self.replace_newlines,
+            spool_threshold: self.spool_threshold,
+            page_size: self.page_size,
+            min_score: self.min_score,
+            min_depth: self.min_depth,
+            min_members: self.min_members,
+            top_level: self.top_level,
         }
     }
 
-    fn handle_string(&mut self) -> Result<Cause, io::Error> {
-        self.jt.advance(CHAR_QUOT_MARK);
-        let mut in_string = true;
-        let mut in_escape = false;
-        let mut in_escaped_unicode = 0;
+    /// The number of bytes that the carver has to remember after consuming
+    /// them, if any.
+    fn history_window(&self) -> Option<usize> {
+        let scoring = self.min_score.map(|_| score::SCORE_WINDOW);
+        let window = match (self.jt.suffix_window, scoring) {
+            (Some(window), Some(scoring)) => Some(window.max(scoring)),
+            (window, scoring) => window.or(scoring),
+        };
+        // The bytes that are scanned again are forgotten first, so that the
+        // bytes before them are still remembered.
+        let replay = match self.jt.max_size {
+            usize::MAX => 0,
+            size => size,
+        };
+        window.map(|window| window + replay.max(self.jt.repair_lookahead.unwrap_or(0)))
+    }
 
-        for b in self.reader.mut_ref().bytes() {
-            let b = b?;
-            // We're at least one indentation level deep when parsing strings,
-            // so we can safely unwrap().
-            let last_ident = self.jt.last_ident().unwrap();
+    /// Create a tracker for scanning the JSON strings that this carver has
+    /// stopped again (see [`JsonTracker::rescanner`]).
+    fn rescanner(&self) -> JsonTracker {
+        self.jt.rescanner()
+    }
 
-            if !in_string {
-                match (b, last_ident, self.jt.in_key) {
-                    (CHAR_SPACE | CHAR_TAB | CHAR_NEWLINE | CHAR_CARRIAGE_RETURN, _, _) => (),
-                    // Case 1: A string value in a JSON list: ["test", "1"]
-                    (CHAR_COMMA | CHAR_RIGHT_SQUARE_BRACKET, CHAR_LEFT_SQUARE_BRACKET, _) => {
-                        return Ok(Cause::Found(b))
-                    }
-                    // Case 2: A value in a JSON object: {"test": "yes", "pain": "right"}
-                    (CHAR_COMMA | CHAR_RIGHT_CURLY_BRACKET, CHAR_LEFT_CURLY_BRACKET, false) => {
-                        return Ok(Cause::Found(b))
-                    }
-                    // Case 3: A key in a JSON object: {"test": 1, "pain": true}
-                    (CHAR_COLON, CHAR_LEFT_CURLY_BRACKET, true) => return Ok(Cause::Found(b)),
-                    (_, _, _) => return Ok(Cause::Corrupted(b)),
-                }
-            } else {
-                match (b, in_escape, in_escaped_unicode) {
-                    (CHAR_ESCAPE, false, 0) => in_escape = true,
-                    (CHAR_QUOT_MARK, false, 0) => in_string = false,
-                    (0x00..0x1F, _, _) => return Ok(Cause::Corrupted(b)),
-                    (_, false, 0) => {
-                        if byte_needs_escape(b) {
-                            return Ok(Cause::Corrupted(b));
-                        }
-                    }
-                    (CHAR_U, true, 0) => {
-                        in_escaped_unicode = 4;
-                        in_escape = false;
-                    }
-                    (_, true, 0) => {
-                        if byte_can_escape(b) {
-                            in_escape = false;
-                        } else {
-                            return Ok(Cause::Corrupted(b));
-                        }
-                    }
-                    (_, _, 1..=4) => {
-                        if b.is_ascii_hexdigit() {
-                            in_escaped_unicode -= 1;
-                        } else {
-                            return Ok(Cause::Corrupted(b));
-                        }
-                    }
-                    (_, _, _) => return Ok(Cause::Corrupted(b)),
-                }
-            }
-            self.jt.advance(b);
-        }
-        Ok(Cause::Exhausted)
-    }
-
-    fn handle_number(&mut self, start_num: u8) -> Result<Cause, io::Error> {
-        self.jt.advance(start_num);
-        let mut in_frac = false;
-        let mut in_exp = false;
-        let mut in_leading_zero: Option<bool> = None;
-
-        for b in self.reader.mut_ref().bytes() {
-            let b = b?;
-            // We've processed at least two bytes in order to be here, so we
-            // can safely unwrap().
-            let last_byte = self.jt.last_byte().unwrap();
-
-            // Check for leading zeroes.
-            //
-            // A leading zero can be preceeded by a minus sign (-), but cannot
-            // be followed by digits.
-            if in_leading_zero == None {
-                in_leading_zero = match last_byte {
-                    CHAR_MINUS => None,
-                    CHAR_ZERO => Some(true),
-                    _ => Some(false),
-                }
+    /// Create a carver without a reader, that is configured like this one.
+    fn fork(&self) -> Carver<io::Empty> {
+        let mut carver = Carver::incremental(None, Some(self.jt.max_ident_depth));
+        carver.jt.max_size = self.jt.max_size;
+        carver.jt.salvage = self.jt.salvage;
+        carver.jt.exhaustive = self.jt.exhaustive;
+        carver.jt.min_string_len = self.jt.min_string_len;
+        carver.jt.strict = self.jt.strict;
+        carver.jt.suffix_window = self.jt.suffix_window;
+        carver.jt.repair_lookahead = self.jt.repair_lookahead;
+        carver.replace_newlines(self.replace_newlines);
+        carver.page_size(self.page_size);
+        carver.min_size = self.min_size;
+        carver.fix_incomplete = self.fix_incomplete;
+        carver.repair_mode = self.repair_mode;
+        carver.spool_threshold = self.spool_threshold;
+        carver.min_score = self.min_score;
+        carver.min_depth = self.min_depth;
+        carver.min_members = self.min_members;
+        carver.top_level = self.top_level;
+        carver.report_all = self.report_all;
+        carver
+    }
+
+    /// Feed a chunk of data to the carver, and pass the JSON strings that it
+    /// completes to `sink`.
+    ///
+    /// The chunk is considered to follow the previously fed data, so JSON
+    /// strings may span multiple chunks, and their positions are relative to
+    /// the start of the first chunk.
+    pub fn feed<S: Sink + ?Sized>(&mut self, chunk: &[u8], sink: &mut S) -> io::Result<()> {
+        let opts = self.options();
+        let history_window = self.history_window();
+        let mut pos = 0;
+        loop {
+            // Bytes that need to be scanned again take precedence over the
+            // rest of the chunk.
+            let replaying = self.replay_pos < self.replay.len();
+            let available = match replaying {
+                true => &self.replay[self.replay_pos..],
+                false => &chunk[pos..],
+            };
+            if available.is_empty() {
+                return Ok(());
             }
-            if in_leading_zero == Some(true) {
-                in_leading_zero = match b {
-                    CHAR_ZERO..=CHAR_NINE => return Ok(Cause::Corrupted(b)),
-                    _ => Some(false),
-                }
+            let (used, replay) = Self::scan(
+                &mut self.jt,
+                &mut self.history,
+                &mut self.spool,
+                &opts,
+                history_window,
+                available,
+                |hit| sink.on_hit(&hit),
+            )?;
+            match replaying {
+                true => self.replay_pos += used,
+                false => pos += used,
             }
-
-            match (last_byte, b) {
-                // Only numbers can follow +/-/..
-                (CHAR_MINUS | CHAR_PLUS | CHAR_DECIMAL, CHAR_ZERO..=CHAR_NINE) => (),
-                // Only numbers or +/- can follow exponent signs.
-                (
-                    CHAR_EXP_LOWER | CHAR_EXP_UPPER,
-                    CHAR_ZERO..=CHAR_NINE | CHAR_MINUS | CHAR_PLUS,
-                ) => (),
-                // Digits, insignificant whitespace, or ,]} can *always* follow
-                // digits.
-                (
-                    CHAR_ZERO..=CHAR_NINE,
-                    CHAR_ZERO..=CHAR_NINE | CHAR_SPACE | CHAR_TAB | CHAR_NEWLINE,
-                ) => (),
-                // Decimal points can follow numbers if we're not in a
-                // fractional/exponent part already.
-                (CHAR_ZERO..=CHAR_NINE, CHAR_DECIMAL) => match (in_frac, in_exp) {
-                    (true, _) | (_, true) => return Ok(Cause::Corrupted(b)),
-                    (false, _) => in_frac = true,
-                },
-                // Exponent signs can follow numbers if we're not in a exponent
-                // part already.
-                (CHAR_ZERO..=CHAR_NINE, CHAR_EXP_LOWER | CHAR_EXP_UPPER) => match in_exp {
-                    true => return Ok(Cause::Corrupted(b)),
-                    false => in_exp = true,
-                },
-                // Numbers are complete only if digits and insignificant
-                // whitespace are followed by ,]}.
-                (
-                    CHAR_SPACE
-                    | CHAR_TAB
-                    | CHAR_NEWLINE
-                    | CHAR_CARRIAGE_RETURN
-                    | CHAR_ZERO..=CHAR_NINE,
-                    CHAR_COMMA | CHAR_RIGHT_SQUARE_BRACKET | CHAR_RIGHT_CURLY_BRACKET,
-                ) => return Ok(Cause::Found(b)),
-                // Everything else is not permitted.
-                (_, _) => return Ok(Cause::Corrupted(b)),
+            if let Some(replay) = replay {
+                self.push_replay(replay);
             }
-            self.jt.advance(b);
         }
-        Ok(Cause::Exhausted)
     }
 
-    fn handle_literal(&mut self, start_char: u8) -> Result<Cause, io::Error> {
-        self.jt.advance(start_char);
-        let literal: &[u8] = match start_char {
-            CHAR_START_FALSE => "alse".as_bytes(),
-            CHAR_START_NULL => "ull".as_bytes(),
-            CHAR_START_TRUE => "rue".as_bytes(),
-            _ => unreachable!(),
+    /// Scan the bytes that are `available`, whether they come from the
+    /// reader, a fed chunk, or the bytes that are scanned again, and pass
+    /// the hits that they complete to `on_hit`.
+    ///
+    /// Return the number of bytes that were used, and the bytes that have to
+    /// be scanned again before the rest, if any.
+    fn scan<F>(
+        jt: &mut JsonTracker,
+        history: &mut Vec<u8>,
+        spool: &mut Spool,
+        opts: &Options,
+        history_window: Option<usize>,
+        available: &[u8],
+        mut on_hit: F,
+    ) -> io::Result<(usize, Option<Vec<u8>>)>
+    where
+        F: FnMut(Hit<'_>) -> io::Result<()>,
+    {
+        let (used, cause) = jt.feed(available);
+        let consumed = &available[..used];
+        if let Some(window) = history_window {
+            suffix::remember(history, consumed, window);
+        }
+        let file = spool.file();
+        let replay = match cause.as_ref().and_then(|cause| jt.rewind(cause, consumed)) {
+            Some(from) => Some(jt.replay(from, consumed, file)?),
+            None => None,
         };
-
-        for (i, b) in self.reader.mut_ref().bytes().enumerate() {
-            let b = b?;
-            if literal[i] != b {
-                return Ok(Cause::Corrupted(b));
-            }
-            self.jt.advance(b);
-            if literal.len() == i + 1 {
-                break;
-            }
+        let resolved = match cause {
+            None => Resolved::default(),
+            Some(Cause::Suffix) => jt.recover_suffix(history, opts),
+            Some(cause) => jt.resolve(cause, consumed, file, history, opts),
+        };
+        for hit in resolved {
+            on_hit(hit)?;
         }
+        if jt.in_progress() {
+            jt.spill(consumed, spool, opts)?;
+        }
+        Ok((used, replay))
+    }
 
-        for b in self.reader.mut_ref().bytes() {
-            let b = b?;
-            match b {
-                CHAR_COMMA | CHAR_RIGHT_SQUARE_BRACKET | CHAR_RIGHT_CURLY_BRACKET => {
-                    return Ok(Cause::Found(b));
+    /// Signal the end of the fed data, and pass the JSON string that is in
+    /// progress, if any, to `sink`.
+    pub fn finish<S: Sink + ?Sized>(&mut self, sink: &mut S) -> io::Result<()> {
+        let opts = self.options();
+        loop {
+            let replay = self.exhaust_replay()?;
+            for hit in self.jt.exhaust(self.spool.file(), &self.history, &opts) {
+                sink.on_hit(&hit)?;
+            }
+            match replay {
+                Some(replay) => {
+                    self.push_replay(replay);
+                    self.feed(&[], sink)?;
                 }
-                CHAR_SPACE | CHAR_TAB | CHAR_NEWLINE | CHAR_CARRIAGE_RETURN => self.jt.advance(b),
-                _ => return Ok(Cause::Corrupted(b)),
+                None => return Ok(()),
             }
         }
-        Ok(Cause::Exhausted)
     }
 
-    fn hunt(&mut self, mut ch: u8) -> Result<Cause, ()> {
-        loop {
-            let res = match ch {
-                CHAR_LEFT_SQUARE_BRACKET => self.handle_left_square_bracket(),
-                CHAR_LEFT_CURLY_BRACKET => self.handle_left_curly_bracket(),
-                CHAR_RIGHT_SQUARE_BRACKET => self.handle_right_square_bracket(),
-                CHAR_RIGHT_CURLY_BRACKET => self.handle_right_curly_bracket(),
-                CHAR_COLON => self.handle_colon(),
-                CHAR_COMMA => self.handle_comma(),
-                CHAR_QUOT_MARK => self.handle_string(),
-                CHAR_MINUS | CHAR_ZERO..=CHAR_NINE => self.handle_number(ch),
-                CHAR_START_FALSE | CHAR_START_NULL | CHAR_START_TRUE => self.handle_literal(ch),
-                _ => {
-                    return Err(());
-                }
-            };
-
-            ch = match res {
-                Ok(Cause::Completed) => {
-                    return Ok(Cause::Completed);
-                }
-                Ok(Cause::Found(ch)) => ch,
-                Ok(Cause::Corrupted(ch)) => {
-                    return Ok(Cause::Corrupted(ch));
-                }
-                Ok(Cause::Exhausted) => {
-                    return Ok(Cause::Exhausted);
-                }
-                Err(_) => {
-                    return Err(()); // FIXME: Capture this error
-                }
-            }
+    /// Return the bytes of the JSON string in progress that have to be
+    /// scanned again once the stream has ended, if any, i.e., the bytes that
+    /// the carver has looked ahead at, past the byte that corrupted it, or the
+    /// bytes after its start, if the carver carves exhaustively.
+    fn exhaust_replay(&self) -> io::Result<Option<Vec<u8>>> {
+        let cause = match (self.jt.state, self.jt.corrupted_at) {
+            (State::Scout | State::Orphan, _) => return Ok(None),
+            (_, Some(_)) => Cause::Corrupted,
+            _ => Cause::Exhausted,
+        };
+        match self.jt.rewind(&cause, &[]) {
+            Some(from) => Ok(Some(self.jt.replay(from, &[], self.spool.file())?)),
+            None => Ok(None),
         }
     }
 
-    fn _print_incomplete(&mut self) -> Result<(), errors::Err> {
-        let w = self.json_writer.mut_ref();
-        w.write_all(&self.jt.processed[..self.jt.partial_close_end + 1])?;
-        for i in (0..self.jt.cur_ident_level).rev() {
-            let closing_ident = _closing_ident(self.jt.ident_levels[i]);
-            w.write_all(&[closing_ident])?;
+    /// Scan the bytes of `replay` again, before the bytes that the carver has
+    /// not scanned yet.
+    fn push_replay(&mut self, mut replay: Vec<u8>) {
+        if let Some(window) = self.history_window() {
+            self.rewind_history(replay.len(), window);
         }
-        w.write_all(&[CHAR_NEWLINE])?;
-        Ok(())
+        replay.extend_from_slice(&self.replay[self.replay_pos..]);
+        self.replay = replay;
+        self.replay_pos = 0;
     }
 
-    /// Start carving a stream of data for JSON strings.
-    pub fn parse(&mut self) -> Result<(), errors::Err> {
-        let mut start = 0;
-        let mut lastb: Option<u8> = None;
+    /// Forget the `n` bytes that were consumed last, since they are about to
+    /// be scanned again.
+    ///
+    /// Only the bytes that were within the last `window` ones are kept, so
+    /// that the carver remembers the same bytes, however the stream is read.
+    fn rewind_history(&mut self, n: usize, window: usize) {
+        let len = self.history.len().saturating_sub(n);
+        self.history.truncate(len);
+        self.history
+            .drain(..len - len.min(window.saturating_sub(n)));
+    }
 
-        loop {
-            let (read, ch) = match lastb {
-                Some(CHAR_LEFT_CURLY_BRACKET) | Some(CHAR_LEFT_SQUARE_BRACKET) => {
-                    // we can safely unwrap() because we're in Some()
-                    (0, lastb.unwrap())
-                }
-                _ => match self.scout() {
-                    Ok(None) => {
-                        break;
-                    }
-                    Ok(Some((read, ch))) => (read, ch),
-                    Err(_) => {
-                        break;
-                    }
-                },
-            };
-            start = start + read - 1;
-            if lastb.is_some() {
-                start += 1;
-            }
+    /// Return a writer that feeds the data written to it to the carver, and
+    /// passes the carved JSON strings to `sink`.
+    pub fn feed_writer<S: Sink>(&mut self, sink: S) -> FeedWriter<'_, R, S> {
+        FeedWriter { carver: self, sink }
+    }
+}
 
-            match self.hunt(ch) {
-                Ok(Cause::Completed) => {
-                    let end = start + self.jt.cur - 1;
-                    let w = self.json_writer.mut_ref();
-                    if self.jt.cur >= self.min_size {
-                        w.write_all(&self.jt.processed[..self.jt.cur])?;
-                        w.write_all(&[CHAR_NEWLINE])?;
-                        if self.report_all {
-                            let report = Report {
-                                status: Cause::Completed,
-                                start: start,
-                                end: end,
-                                partial_end: end,
-                            };
-                            report.print(&mut self.report_writer)?;
-                        }
-                    }
-                    start = end + 1;
-                    lastb = None;
-                }
-                Ok(Cause::Corrupted(ch)) => {
-                    let corrupted_end = start + self.jt.cur - 1;
-                    let partial_end = start + self.jt.partial_close_end;
-                    if self.jt.partial_close_end >= self.min_size {
-                        let report = Report {
-                            status: Cause::Corrupted(ch),
-                            start: start,
-                            end: corrupted_end,
-                            partial_end: partial_end,
-                        };
-                        report.print(&mut self.report_writer)?;
-                        if self.fix_incomplete {
-                            self._print_incomplete()?
-                        }
+impl<R: BufRead> Carver<R> {
+    /// Return an iterator over the JSON strings in the stream. Strings that
+    /// are smaller than `min_size` are skipped.
+    pub fn carve(&mut self) -> Carve<'_, R> {
+        Carve { carver: self }
+    }
+
+    /// Carve the stream and pass the JSON strings that should be reported to
+    /// `on_hit`, until it breaks or the stream ends.
+    ///
+    /// The hits borrow the buffer of the reader, so they are passed by value.
+    fn drive<F>(&mut self, mut on_hit: F) -> Result<(), errors::Err>
+    where
+        F: FnMut(Hit<'_>) -> io::Result<ControlFlow<()>>,
+    {
+        let opts = self.options();
+        let history_window = self.history_window();
+        while let Some(hit) = self.pending.pop_front() {
+            if on_hit(hit)?.is_break() {
+                return Ok(());
+            }
+        }
+        while !self.done {
+            // Bytes that need to be scanned again take precedence over the
+            // bytes of the reader.
+            let replaying = self.replay_pos < self.replay.len();
+            let available = match replaying {
+                true => Ok(&self.replay[self.replay_pos..]),
+                false => self.reader.fill_buf(),
+            };
+            let available = match available {
+                Ok(n) => n,
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e.into()),
+            };
+            if available.is_empty() {
+                let replay = self.exhaust_replay()?;
+                let mut flow = ControlFlow::Continue(());
+                for hit in self.jt.exhaust(self.spool.file(), &self.history, &opts) {
+                    match flow {
+                        ControlFlow::Continue(()) => flow = on_hit(hit)?,
+                        ControlFlow::Break(()) => self.pending.push_back(hit.into_owned()?),
                     }
-                    start = corrupted_end + 1;
-                    lastb = Some(ch);
                 }
-                Ok(Cause::Exhausted) => {
-                    let corrupted_end = start + self.jt.cur - 1;
-                    let partial_end = start + self.jt.partial_close_end;
-                    if self.jt.partial_close_end >= self.min_size {
-                        let report = Report {
-                            status: Cause::Exhausted,
-                            start: start,
-                            end: corrupted_end,
-                            partial_end: partial_end,
-                        };
-                        report.print(&mut self.report_writer)?;
-                        if self.fix_incomplete {
-                            self._print_incomplete()?
-                        }
-                    }
-                    break;
+                match replay {
+                    Some(replay) => self.push_replay(replay),
+                    None => self.done = true,
                 }
-                Ok(Cause::Found(_)) => unreachable!(),
-                Err(_) => {
+                if flow.is_break() {
                     break;
                 }
-            };
-            self.jt.quick_clean();
+                continue;
+            }
+            // Keep the hits that are left after a break for later.
+            let mut flow = ControlFlow::Continue(());
+            let pending = &mut self.pending;
+            let (used, replay) = Self::scan(
+                &mut self.jt,
+                &mut self.history,
+                &mut self.spool,
+                &opts,
+                history_window,
+                available,
+                |hit| {
+                    match flow {
+                        ControlFlow::Continue(()) => flow = on_hit(hit)?,
+                        ControlFlow::Break(()) => pending.push_back(hit.into_owned()?),
+                    }
+                    Ok(())
+                },
+            )?;
+            match replaying {
+                true => self.replay_pos += used,
+                false => self.reader.consume(used),
+            }
+            if let Some(replay) = replay {
+                self.push_replay(replay);
+            }
+            if flow.is_break() {
+                break;
+            }
         }
         Ok(())
     }
+
+    /// Carve the next JSON string that should be reported, if any.
+    fn next_hit(&mut self) -> Result<Option<Hit<'static>>, errors::Err> {
+        let mut next = None;
+        self.drive(|hit| {
+            next = Some(hit.into_owned()?);
+            Ok(ControlFlow::Break(()))
+        })?;
+        Ok(next)
+    }
+
+    /// Carve a stream of data for JSON strings, and pass them to `sink`.
+    pub fn parse<S: Sink + ?Sized>(&mut self, sink: &mut S) -> Result<(), errors::Err> {
+        self.drive(|hit| {
+            sink.on_hit(&hit)?;
+            Ok(ControlFlow::Continue(()))
+        })
+    }
+
+    /// Carve a stream of data for JSON strings, and write them to
+    /// `json_writer`. Corrupted strings are reported to `report_writer`, and
+    /// so are the rest, if `report_all` is set.
+    ///
+    /// This is a convenience for [`Carver::parse`] with a [`JsonLines`] and a
+    /// [`CsvReport`] sink.
+    pub fn parse_writers<J: Write, W: Write>(
+        &mut self,
+        json_writer: J,
+        report_writer: W,
+    ) -> Result<(), errors::Err> {
+        let mut sink = (
+            JsonLines::new(json_writer),
+            CsvReport::new(report_writer, self.report_all),
+        );
+        self.parse(&mut sink)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use rstest::rstest;
     use std::fs;
-    use std::path::PathBuf;
+    use std::path::{Path, PathBuf};
 
     use super::*;
 
-    fn create_carver<'a>(buf: &'a [u8]) -> Carver<'a> {
-        let reader = BufReader::new(buf);
-        let json_writer = BufWriter::new(vec![]);
-        let report_writer = BufWriter::new(vec![]);
-        let mut carver = Carver::new(
-            Reader::Local(reader),
-            Writer::Local(json_writer),
-            Writer::Local(report_writer),
-            None,
-            None,
-        );
+    /// Create a carver that reports JSON strings of any size.
+    pub(crate) fn create_carver(buf: &[u8]) -> Carver<&[u8]> {
+        let mut carver = Carver::new(buf, None, None);
         carver.min_size = 0;
         carver
     }
 
-    fn get_buf(writer: &Writer) -> Vec<u8> {
-        let mut res_buf = match writer {
-            Writer::Local(w) => w.buffer().to_vec(),
-            _ => unreachable!(),
-        };
+    /// Carve `buf` with a carver that reports JSON strings of any size, and
+    /// that `setup` configures further, and return the JSON strings.
+    pub(crate) fn carve_with<'a>(
+        buf: &'a [u8],
+        setup: impl FnOnce(&mut Carver<&'a [u8]>),
+    ) -> Vec<Hit<'static>> {
+        let mut carver = create_carver(buf);
+        setup(&mut carver);
+        carver.carve().map(|hit| hit.unwrap()).collect()
+    }
+
+    fn get_buf(mut res_buf: Vec<u8>) -> Vec<u8> {
         if res_buf.last() == Some(&CHAR_NEWLINE) {
             res_buf.pop();
         }
@@ -994,9 +2672,10 @@ mod tests {
         let buf_disp = String::from_utf8_lossy(buf);
         eprintln!("### Evaluating buffer: {buf_disp}");
         let mut carver = create_carver(buf);
-        let res = carver.parse();
+        let mut json_writer = vec![];
+        let res = carver.parse(&mut JsonLines::new(&mut json_writer));
         assert!(res.is_ok());
-        let res_buf = get_buf(&carver.json_writer);
+        let res_buf = get_buf(json_writer);
         let res_buf_disp = String::from_utf8_lossy(&res_buf);
         eprintln!("### Result is: {res_buf_disp}");
         res_buf
@@ -1007,10 +2686,16 @@ mod tests {
         eprintln!("### Evaluating buffer: {buf_disp}");
         let mut carver = create_carver(buf);
         carver.fix_incomplete = fix;
-        let res = carver.parse();
+        let mut json_writer = vec![];
+        let mut report_writer = vec![];
+        let mut sink = (
+            JsonLines::new(&mut json_writer),
+            CsvReport::new(&mut report_writer, false),
+        );
+        let res = carver.parse(&mut sink);
         assert!(res.is_ok());
-        let json_buf = get_buf(&carver.json_writer);
-        let report_buf = get_buf(&carver.report_writer);
+        let json_buf = get_buf(json_writer);
+        let report_buf = get_buf(report_writer);
         let json_buf_disp = String::from_utf8_lossy(&json_buf);
         let report_buf_disp = String::from_utf8_lossy(&report_buf);
         eprintln!("### Result is: {json_buf_disp}");
@@ -1161,6 +2846,49 @@ mod tests {
         assert_eq!(report, report_expected.as_bytes());
     }
 
+    #[rstest]
+    #[case(
+        br#"{"msg": "a long message that got cut"#,
+        br#"{"msg": "a long message that got cut"}"#
+    )]
+    #[case(br#"{"a": 1, "b""#, br#"{"a": 1}"#)]
+    #[case(br#"{"a": 1, "b": "#, br#"{"a": 1}"#)]
+    #[case(br#"{"a": "b" x"#, br#"{"a": "b"}"#)]
+    #[case(br#"{"key"#, b"{}")]
+    #[case(b"[1, 2, ", b"[1, 2]")]
+    #[case(b"[1, 23", b"[1]")]
+    #[case(br#"["a", tru"#, br#"["a"]"#)]
+    #[case(
+        br#"{"a": [true, {"b": null}, "x\"#,
+        br#"{"a": [true, {"b": null}, "x"]}"#
+    )]
+    #[case(br#"["\u00"#, br#"[""]"#)]
+    #[case(b"[\"caf\xc3", br#"["caf"]"#)]
+    #[case(b"[\"caf\xc3\xa9", b"[\"caf\xc3\xa9\"]")]
+    fn test_repair_values(#[case] buf: &[u8], #[case] expected: &[u8]) {
+        let mut carver = create_carver(buf);
+        carver.fix_incomplete = true;
+        carver.repair_mode = RepairMode::Values;
+        let hit = carver.carve().next().unwrap().unwrap();
+        assert_eq!(hit.repaired.unwrap(), expected);
+    }
+
+    #[rstest]
+    fn json_test_suite_repair(#[files("tests/JSONTestSuite/**/y_*.json")] path: PathBuf) {
+        // Every prefix of a valid JSON string is repaired to a valid one.
+        let buf: Vec<u8> = fs::read(path).unwrap();
+        for end in 1..buf.len() {
+            let mut carver = create_carver(&buf[..end]);
+            carver.fix_incomplete = true;
+            carver.repair_mode = RepairMode::Values;
+            for hit in carver.carve() {
+                if let Some(repaired) = hit.unwrap().repaired {
+                    assert_eq!(parse(&repaired), repaired);
+                }
+            }
+        }
+    }
+
     #[rstest]
     fn json_test_suite_success(#[files("tests/JSONTestSuite/**/y_*.json")] path: PathBuf) {
         let buf: Vec<u8> = fs::read(path).unwrap();
@@ -1169,12 +2897,170 @@ mod tests {
             res_buf.pop();
         }
         assert_eq!(parse(&buf), res_buf);
+
+        // Valid JSON strings are valid Unicode as well.
+        let hits = carve_with(&buf, |carver| carver.strict(true));
+        assert!(hits.iter().all(|hit| hit.status == Status::Completed));
     }
 
     #[rstest]
-    fn json_test_suite_impl(#[files("tests/JSONTestSuite/**/i_*.json")] path: PathBuf) {
-        let buf: Vec<u8> = fs::read(path).unwrap();
-        parse(&buf);
+    #[case(
+        "i_number_double_huge_neg_exp.json",
+        Status::Completed,
+        Status::Completed
+    )]
+    #[case("i_number_huge_exp.json", Status::Completed, Status::Completed)]
+    #[case("i_number_neg_int_huge_exp.json", Status::Completed, Status::Completed)]
+    #[case(
+        "i_number_pos_double_huge_exp.json",
+        Status::Completed,
+        Status::Completed
+    )]
+    #[case(
+        "i_number_real_neg_overflow.json",
+        Status::Completed,
+        Status::Completed
+    )]
+    #[case(
+        "i_number_real_pos_overflow.json",
+        Status::Completed,
+        Status::Completed
+    )]
+    #[case("i_number_real_underflow.json", Status::Completed, Status::Completed)]
+    #[case("i_number_too_big_neg_int.json", Status::Completed, Status::Completed)]
+    #[case("i_number_too_big_pos_int.json", Status::Completed, Status::Completed)]
+    #[case(
+        "i_number_very_big_negative_int.json",
+        Status::Completed,
+        Status::Completed
+    )]
+    #[case(
+        "i_object_key_lone_2nd_surrogate.json",
+        Status::Completed,
+        Status::InvalidUnicode
+    )]
+    #[case(
+        "i_string_1st_surrogate_but_2nd_missing.json",
+        Status::Completed,
+        Status::InvalidUnicode
+    )]
+    #[case(
+        "i_string_1st_valid_surrogate_2nd_invalid.json",
+        Status::Completed,
+        Status::InvalidUnicode
+    )]
+    #[case(
+        "i_string_UTF-16LE_with_BOM.json",
+        Status::Corrupted,
+        Status::Corrupted
+    )]
+    #[case(
+        "i_string_UTF-8_invalid_sequence.json",
+        Status::Completed,
+        Status::InvalidUnicode
+    )]
+    #[case(
+        "i_string_UTF8_surrogate_U+D800.json",
+        Status::Completed,
+        Status::InvalidUnicode
+    )]
+    #[case(
+        "i_string_incomplete_surrogate_and_escape_valid.json",
+        Status::Completed,
+        Status::InvalidUnicode
+    )]
+    #[case(
+        "i_string_incomplete_surrogate_pair.json",
+        Status::Completed,
+        Status::InvalidUnicode
+    )]
+    #[case(
+        "i_string_incomplete_surrogates_escape_valid.json",
+        Status::Completed,
+        Status::InvalidUnicode
+    )]
+    #[case(
+        "i_string_invalid_lonely_surrogate.json",
+        Status::Completed,
+        Status::InvalidUnicode
+    )]
+    #[case(
+        "i_string_invalid_surrogate.json",
+        Status::Completed,
+        Status::InvalidUnicode
+    )]
+    #[case(
+        "i_string_invalid_utf-8.json",
+        Status::Completed,
+        Status::InvalidUnicode
+    )]
+    #[case(
+        "i_string_inverted_surrogates_U+1D11E.json",
+        Status::Completed,
+        Status::InvalidUnicode
+    )]
+    #[case("i_string_iso_latin_1.json", Status::Completed, Status::InvalidUnicode)]
+    #[case(
+        "i_string_lone_second_surrogate.json",
+        Status::Completed,
+        Status::InvalidUnicode
+    )]
+    #[case(
+        "i_string_lone_utf8_continuation_byte.json",
+        Status::Completed,
+        Status::InvalidUnicode
+    )]
+    #[case(
+        "i_string_not_in_unicode_range.json",
+        Status::Completed,
+        Status::InvalidUnicode
+    )]
+    #[case(
+        "i_string_overlong_sequence_2_bytes.json",
+        Status::Completed,
+        Status::InvalidUnicode
+    )]
+    #[case(
+        "i_string_overlong_sequence_6_bytes.json",
+        Status::Completed,
+        Status::InvalidUnicode
+    )]
+    #[case(
+        "i_string_overlong_sequence_6_bytes_null.json",
+        Status::Completed,
+        Status::InvalidUnicode
+    )]
+    #[case(
+        "i_string_truncated-utf-8.json",
+        Status::Completed,
+        Status::InvalidUnicode
+    )]
+    #[case("i_string_utf16BE_no_BOM.json", Status::Corrupted, Status::Corrupted)]
+    #[case("i_string_utf16LE_no_BOM.json", Status::Corrupted, Status::Corrupted)]
+    #[case(
+        "i_structure_500_nested_arrays.json",
+        Status::Completed,
+        Status::Completed
+    )]
+    #[case(
+        "i_structure_UTF-8_BOM_empty_object.json",
+        Status::Completed,
+        Status::Completed
+    )]
+    fn json_test_suite_impl(#[case] name: &str, #[case] lenient: Status, #[case] strict: Status) {
+        let buf: Vec<u8> =
+            fs::read(Path::new("tests/JSONTestSuite/test_parsing").join(name)).unwrap();
+        // The byte order mark is skipped like any other byte.
+        let json = get_buf(buf.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(&buf).to_vec());
+        for (opt, expected) in [(false, lenient), (true, strict)] {
+            let mut carver = create_carver(&buf);
+            carver.strict(opt);
+            let hit = carver.carve().next().unwrap().unwrap();
+            assert_eq!(hit.status, expected);
+            if expected == Status::Completed {
+                assert_eq!(hit.data, json);
+            }
+        }
     }
 
     #[rstest]
@@ -1187,11 +3073,759 @@ mod tests {
         assert_eq!(parse(&buf).len(), 0);
     }
 
+    #[rstest]
+    fn json_test_suite_mmap(
+        #[files("tests/JSONTestSuite/**/*.json")]
+        #[files("tests/test_*/*.json")]
+        path: PathBuf,
+    ) {
+        let collect = |reader: &mut dyn BufRead| -> Vec<Hit> {
+            let mut carver = Carver::new(reader, None, None);
+            carver.min_size = 0;
+            carver.fix_incomplete = true;
+            carver.carve().map(|hit| hit.unwrap()).collect()
+        };
+        let buf: Vec<u8> = fs::read(&path).unwrap();
+        let expected = collect(&mut &buf[..]);
+        let file = File::open(&path).unwrap();
+        assert_eq!(collect(&mut Reader::from_mmap(&file).unwrap()), expected);
+        let file = File::open(&path).unwrap();
+        assert_eq!(collect(&mut BufReader::with_capacity(3, file)), expected);
+    }
+
     #[rstest]
     fn json_test_suite_partial(#[files("tests/test_partial/*.json")] path: PathBuf) {
         let buf: Vec<u8> = fs::read(path).unwrap();
         let res = parse(&buf);
-        assert!(res.len() > 0);
+        assert!(!res.is_empty());
         assert_ne!(res, buf);
     }
+
+    #[test]
+    fn test_carve() {
+        let buf = r#"[1, 2]00{"a": [true, {"b": nul"#;
+        let mut carver = create_carver(buf.as_bytes());
+        carver.fix_incomplete = true;
+        let hits: Vec<Hit> = carver.carve().map(|hit| hit.unwrap()).collect();
+        assert_eq!(
+            hits,
+            [
+                Hit {
+                    status: Status::Completed,
+                    start: 0,
+                    end: 5,
+                    partial_end: 5,
+                    data: b"[1, 2]"[..].into(),
+                    repaired: None,
+                    spooled: None,
+                    candidates: vec![],
+                    score: None,
+                    parent: None,
+                    encoding: None,
+                    object: None,
+                },
+                Hit {
+                    status: Status::Exhausted,
+                    start: 8,
+                    end: 29,
+                    partial_end: 21,
+                    data: br#"{"a": [true, {"b": nul"#[..].into(),
+                    repaired: Some(br#"{"a": [true, {}]}"#.to_vec()),
+                    spooled: None,
+                    candidates: vec![],
+                    score: None,
+                    parent: None,
+                    encoding: None,
+                    object: None,
+                },
+            ]
+        );
+        assert_eq!(hits[0].len(), 6);
+        assert!(carver.carve().next().is_none());
+    }
+
+    #[test]
+    fn test_reader_writer() {
+        let buf = b"00[1, 2]00{\"a\"";
+        let reader = Reader::Local(BufReader::new(&buf[..]));
+        let mut json_writer = Writer::Local(BufWriter::new(vec![]));
+        let mut report_writer = Writer::Local(BufWriter::new(vec![]));
+        let mut carver = Carver::new(reader, None, None);
+        carver.min_size = 0;
+        carver.report_all = true;
+        assert!(
+            carver
+                .parse_writers(&mut json_writer, &mut report_writer)
+                .is_ok()
+        );
+        match (json_writer, report_writer) {
+            (Writer::Local(json_writer), Writer::Local(report_writer)) => {
+                assert_eq!(json_writer.buffer(), b"[1, 2]\n");
+                assert_eq!(
+                    report_writer.buffer(),
+                    b"completed,2,7,7\nexhausted,10,13,10\n"
+                );
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn test_cursor() {
+        let buf = io::Cursor::new(br#"xx{"owned": "buffer"}xx"#.to_vec());
+        let mut carver = Carver::new(buf, None, None);
+        let hits: Vec<Hit> = carver.carve().map(|hit| hit.unwrap()).collect();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].start, 2);
+        assert_eq!(&hits[0].data[..], br#"{"owned": "buffer"}"#);
+    }
+
+    #[derive(Default)]
+    struct CountingSink {
+        completed: Vec<usize>,
+        corrupted: Vec<usize>,
+        exhausted: Vec<usize>,
+        repaired: Vec<Vec<u8>>,
+    }
+
+    impl Sink for CountingSink {
+        fn on_completed(&mut self, hit: &Hit) -> io::Result<()> {
+            self.completed.push(hit.start);
+            Ok(())
+        }
+
+        fn on_corrupted(&mut self, hit: &Hit) -> io::Result<()> {
+            self.corrupted.push(hit.start);
+            Ok(())
+        }
+
+        fn on_exhausted(&mut self, hit: &Hit) -> io::Result<()> {
+            self.exhausted.push(hit.start);
+            Ok(())
+        }
+
+        fn on_repaired(&mut self, _hit: &Hit, repaired: &[u8]) -> io::Result<()> {
+            self.repaired.push(repaired.to_vec());
+            Ok(())
+        }
+    }
+
+    #[derive(Default)]
+    struct CollectingSink {
+        hits: Vec<Hit<'static>>,
+    }
+
+    impl Sink for CollectingSink {
+        fn on_hit(&mut self, hit: &Hit) -> io::Result<()> {
+            self.hits.push(hit.clone().into_owned()?);
+            Ok(())
+        }
+    }
+
+    type DynCarver<'a> = Carver<Box<dyn BufRead + 'a>>;
+
+    /// Assert that every way of reading `buf` finds the same JSON strings as
+    /// reading it at once, i.e., reading it with buffers of each of `sizes`,
+    /// while spooling unless `setup` turns it off, and feeding it in chunks
+    /// of each of `sizes`. `setup` configures every carver. Return the JSON
+    /// strings.
+    fn assert_same_hits<'a>(
+        buf: &'a [u8],
+        sizes: impl IntoIterator<Item = usize>,
+        setup: impl Fn(&mut DynCarver<'a>),
+    ) -> Vec<Hit<'static>> {
+        let create = |reader: Box<dyn BufRead + 'a>, spool_threshold| {
+            let mut carver = Carver::new(reader, None, None);
+            carver.min_size = 0;
+            carver.spool_threshold = spool_threshold;
+            setup(&mut carver);
+            carver
+        };
+        let mut carver = create(Box::new(buf), None);
+        let expected: Vec<Hit> = carver
+            .carve()
+            .map(|hit| hit.unwrap().into_owned().unwrap())
+            .collect();
+        for size in sizes {
+            let reader = Box::new(BufReader::with_capacity(size, buf));
+            let mut carver = create(reader, Some(2));
+            let hits: Vec<Hit> = carver.carve().map(|hit| hit.unwrap()).collect();
+            assert_eq!(hits, expected, "buffer size {size}");
+
+            let mut carver = create(Box::new(io::empty()), None);
+            let mut sink = CollectingSink::default();
+            for chunk in buf.chunks(size) {
+                carver.feed(chunk, &mut sink).unwrap();
+            }
+            carver.finish(&mut sink).unwrap();
+            assert_eq!(sink.hits, expected, "chunk size {size}");
+        }
+        expected
+    }
+
+    #[test]
+    fn test_feed() {
+        let bufs = [
+            r#"[1]{"key":"val":  [2],[fal[3]]]{"a""#,
+            r#"xx{"test": {"inside": [1, 2.5e-3, "\u00e9"]}}{"#,
+            r#"[1, 2, 3, {"test"[true, null, far{"key": "value",[9]"#,
+        ];
+        for buf in bufs {
+            let mut carver = create_carver(buf.as_bytes());
+            carver.fix_incomplete = true;
+            let expected: Vec<Hit> = carver.carve().map(|hit| hit.unwrap()).collect();
+
+            for size in 1..=buf.len() {
+                let mut carver = Carver::incremental(None, None);
+                carver.min_size = 0;
+                carver.fix_incomplete = true;
+                let mut sink = CollectingSink::default();
+                for chunk in buf.as_bytes().chunks(size) {
+                    carver.feed(chunk, &mut sink).unwrap();
+                }
+                carver.finish(&mut sink).unwrap();
+                assert_eq!(sink.hits, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_feed_writer() {
+        let mut carver = Carver::incremental(None, None);
+        let mut writer = carver.feed_writer(JsonLines::new(vec![]));
+        write!(writer, "garbage [1, ").unwrap();
+        write!(writer, "2, 3] more garbage {{\"a\": ").unwrap();
+        write!(writer, "true}}").unwrap();
+        let sink = writer.finish().unwrap();
+        assert_eq!(sink.into_inner(), b"[1, 2, 3]\n{\"a\": true}\n");
+    }
+
+    #[test]
+    fn test_custom_sink() {
+        let buf = r#"[1]{"key":"val":  [2],[fal[3]]]{"a""#;
+        let mut carver = create_carver(buf.as_bytes());
+        carver.fix_incomplete = true;
+        let mut sink = CountingSink::default();
+        assert!(carver.parse(&mut sink).is_ok());
+        assert_eq!(sink.completed, [0, 18, 26]);
+        assert_eq!(sink.corrupted, [3, 22]);
+        assert_eq!(sink.exhausted, [31]);
+        assert_eq!(sink.repaired, [b"{}", b"[]", b"{}"]);
+    }
+
+    #[derive(Default)]
+    struct BorrowingSink {
+        borrowed: Vec<bool>,
+    }
+
+    impl Sink for BorrowingSink {
+        fn on_hit(&mut self, hit: &Hit) -> io::Result<()> {
+            self.borrowed.push(matches!(hit.data, Cow::Borrowed(_)));
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_zero_copy() {
+        let buf = "[1]\n{\"a\":\n2}[3, \"x\"";
+        let borrowed = |reader: &mut dyn BufRead, replace_newlines| {
+            let mut carver = Carver::new(reader, None, None);
+            carver.min_size = 0;
+            carver.replace_newlines(replace_newlines);
+            let mut sink = BorrowingSink::default();
+            carver.parse(&mut sink).unwrap();
+            sink.borrowed
+        };
+        assert_eq!(borrowed(&mut buf.as_bytes(), false), [true, true, false]);
+        // Only strings with newlines need to be transformed.
+        assert_eq!(borrowed(&mut buf.as_bytes(), true), [true, false, false]);
+        // Strings that span multiple buffers need to be copied.
+        let mut reader = BufReader::with_capacity(6, buf.as_bytes());
+        assert_eq!(borrowed(&mut reader, false), [true, false, false]);
+    }
+
+    #[derive(Default)]
+    struct SpoolingSink {
+        hits: Vec<Hit<'static>>,
+        spooled: Vec<usize>,
+    }
+
+    impl Sink for SpoolingSink {
+        fn on_hit(&mut self, hit: &Hit) -> io::Result<()> {
+            self.spooled
+                .push(hit.spooled.map_or(0, |spooled| spooled.len()));
+            self.hits.push(hit.clone().into_owned()?);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_spool() {
+        let buf = "[1]{\"key\":\n\"a long value\", \"b\": [1, 2]}[[\"x\"], {\"y\": tru";
+        let run = |spool_threshold| {
+            let mut carver = Carver::new(BufReader::with_capacity(3, buf.as_bytes()), None, None);
+            carver.min_size = 0;
+            carver.fix_incomplete = true;
+            carver.replace_newlines(true);
+            carver.spool_threshold = spool_threshold;
+            let mut sink = (JsonLines::new(vec![]), SpoolingSink::default());
+            carver.parse(&mut sink).unwrap();
+            (sink.0.into_inner(), sink.1)
+        };
+        let (expected_json, expected) = run(None);
+        assert_eq!(expected.spooled, [0, 0, 0]);
+        assert_eq!(
+            String::from_utf8(expected_json.clone()).unwrap(),
+            "[1]\n{\"key\": \"a long value\", \"b\": [1, 2]}\n[[\"x\"], {}]\n"
+        );
+        for threshold in 1..8 {
+            let (json, sink) = run(Some(threshold));
+            assert_eq!(json, expected_json);
+            assert_eq!(sink.hits, expected.hits);
+            assert!(sink.spooled[1] > 0 && sink.spooled[2] > 0);
+        }
+    }
+
+    #[test]
+    fn test_too_deep() {
+        let buf = "[[[1]]] [[[[2]]]]";
+        let mut carver = create_carver(buf.as_bytes());
+        carver.fix_incomplete = true;
+        carver.max_depth(3);
+        let mut json_writer = vec![];
+        let mut report_writer = vec![];
+        let mut sink = (
+            JsonLines::new(&mut json_writer),
+            CsvReport::new(&mut report_writer, false),
+        );
+        carver.parse(&mut sink).unwrap();
+        assert_eq!(json_writer, b"[[[1]]]\n[[[]]]\n[2]\n");
+        assert_eq!(report_writer, b"too_deep,8,10,10\n");
+
+        // The identation stack grows as needed.
+        let buf = "[".repeat(100_000) + &"]".repeat(100_000);
+        assert_eq!(parse(buf.as_bytes()), buf.as_bytes());
+    }
+
+    #[test]
+    fn test_too_large() {
+        let buf = "[1, [2, 3], 4] {\"a\": \"a long string\"} x";
+        let mut carver = create_carver(buf.as_bytes());
+        carver.fix_incomplete = true;
+        carver.max_size(Some(8));
+        let mut json_writer = vec![];
+        let mut report_writer = vec![];
+        let mut sink = (
+            JsonLines::new(&mut json_writer),
+            CsvReport::new(&mut report_writer, false),
+        );
+        carver.parse(&mut sink).unwrap();
+        assert_eq!(json_writer, b"[1, []]\n[2, 3]\n{}\n");
+        assert_eq!(report_writer, b"too_large,0,7,4\ntoo_large,15,22,15\n");
+
+        // Every way of reading the stream rescans the same bytes.
+        assert_same_hits(buf.as_bytes(), 1..=buf.len(), |carver| {
+            carver.max_size(Some(8));
+        });
+    }
+
+    #[test]
+    fn test_salvage() {
+        let buf = r#"[1, {"a": {"msg": "hello"}}, <garbage> [{"b": 2}, [3]"#;
+        let mut carver = create_carver(buf.as_bytes());
+        carver.salvage(true);
+        let mut json_writer = vec![];
+        let mut report_writer = vec![];
+        let mut sink = (
+            JsonLines::new(&mut json_writer),
+            CsvReport::new(&mut report_writer, false),
+        );
+        carver.parse(&mut sink).unwrap();
+        assert_eq!(
+            json_writer,
+            b"{\"a\": {\"msg\": \"hello\"}}\n{\"b\": 2}\n[3]\n"
+        );
+        assert_eq!(
+            report_writer,
+            b"corrupted,0,28,26\nsalvaged,4,26,26\nexhausted,39,52,52\nsalvaged,40,47,47\nsalvaged,50,52,52\n"
+        );
+
+        // Every way of reading the stream salvages the same JSON strings.
+        let expected = assert_same_hits(buf.as_bytes(), 1..=buf.len(), |carver| {
+            carver.salvage(true);
+        });
+        assert_eq!(expected.len(), 5);
+    }
+
+    #[test]
+    fn test_recover_suffixes() {
+        let buf = r#"xx"body": "text"}, {"id": 5}]} x 1, 2]] "#;
+        let mut carver = create_carver(buf.as_bytes());
+        carver.recover_suffixes(Some(DEFAULT_SUFFIX_WINDOW));
+        let mut json_writer = vec![];
+        let mut report_writer = vec![];
+        let mut sink = (
+            JsonLines::new(&mut json_writer),
+            CsvReport::new(&mut report_writer, false),
+        );
+        carver.parse(&mut sink).unwrap();
+        assert_eq!(
+            String::from_utf8(json_writer).unwrap(),
+            "{\"id\": 5}\n\
+             {\"\":[{\"body\": \"text\"}, {\"id\": 5}]}\n\
+             [[1, 2]]\n"
+        );
+        assert_eq!(
+            report_writer,
+            b"suffix_recovered,2,29,29\nsuffix_recovered,33,38,38\n"
+        );
+
+        // The suffix must fit in the window.
+        let hits = carve_with(buf.as_bytes(), |carver| carver.recover_suffixes(Some(20)));
+        assert_eq!(
+            hits[1].repaired.as_deref(),
+            Some(&br#"{"":[{"id": 5}]}"#[..])
+        );
+
+        // Every way of reading the stream recovers the same suffixes.
+        assert_same_hits(buf.as_bytes(), 1..=buf.len(), |carver| {
+            carver.recover_suffixes(Some(24));
+        });
+    }
+
+    #[test]
+    fn test_rank_repairs() {
+        let buf = "[1, 2,] x {\"a\": \"b\x00c\", \"d\": [3]}{\"e\": tr";
+        let run = |carver: &mut Carver<&[u8]>| {
+            let mut json_writer = vec![];
+            let mut log_writer = vec![];
+            let mut sink = (
+                JsonLines::new(&mut json_writer),
+                RepairLog::new(&mut log_writer),
+            );
+            carver.parse(&mut sink).unwrap();
+            (json_writer, log_writer)
+        };
+        let mut carver = create_carver(buf.as_bytes());
+        let (expected_json, _) = run(&mut carver);
+        let mut carver = create_carver(buf.as_bytes());
+        carver.rank_repairs(Some(DEFAULT_REPAIR_LOOKAHEAD));
+        let (json, log) = run(&mut carver);
+        // Looking ahead does not change the JSON strings that are found.
+        assert_eq!(json, expected_json);
+        let log = String::from_utf8(log).unwrap();
+        assert_eq!(
+            log.lines().collect::<Vec<_>>(),
+            [
+                r#"{"start":0,"end":17,"rank":1,"strategy":"skip_corrupted","retained":15,"edits":[{"drop":[6,8]},{"insert":[18,"\"}]"]}],"repaired":"[1, 2, {\"a\": \"b\"}]"}"#,
+                r#"{"start":0,"end":6,"rank":2,"strategy":"drop_trailing_comma","retained":6,"edits":[{"drop":[5,5]}],"repaired":"[1, 2]"}"#,
+                r#"{"start":10,"end":31,"rank":1,"strategy":"skip_corrupted","retained":21,"edits":[{"drop":[18,18]}],"repaired":"{\"a\": \"bc\", \"d\": [3]}"}"#,
+                r#"{"start":10,"end":17,"rank":2,"strategy":"close_string","retained":8,"edits":[{"insert":[18,"\"}"]}],"repaired":"{\"a\": \"b\"}"}"#,
+                r#"{"start":10,"end":17,"rank":3,"strategy":"truncate","retained":1,"edits":[{"drop":[11,17]},{"insert":[18,"}"]}],"repaired":"{}"}"#,
+                r#"{"start":32,"end":39,"rank":1,"strategy":"truncate","retained":1,"edits":[{"drop":[33,39]},{"insert":[40,"}"]}],"repaired":"{}"}"#,
+            ]
+        );
+
+        // The repaired JSON strings are escaped, whatever their bytes are.
+        let mut log = vec![];
+        sink::write_json_string(&mut log, b"[\"a\nb\x01\xff\"]").unwrap();
+        assert_eq!(log, br#""[\"a\nb\u0001\u00ff\"]""#);
+
+        // Every way of reading the stream ranks the same repairs.
+        assert_same_hits(buf.as_bytes(), 1..=buf.len(), |carver| {
+            carver.rank_repairs(Some(8));
+            // Spooled JSON strings are not ranked.
+            carver.spool_threshold = None;
+        });
+    }
+
+    #[test]
+    fn test_page_size() {
+        let buf = "xx[1, 2,\x00{\"a\": [3]}\x00[4, 5\x00";
+        let mut carver = create_carver(buf.as_bytes());
+        carver.page_size(Some(8));
+        let mut report_writer = vec![];
+        let mut report = CsvReport::new(&mut report_writer, true);
+        report.page_size(Some(8));
+        carver.parse(&mut report).unwrap();
+        assert_eq!(
+            String::from_utf8(report_writer).unwrap(),
+            "page_boundary,2,7,2,\ncompleted,9,18,18,16\ncorrupted,20,24,20,24\n"
+        );
+
+        // Every way of reading the stream finds the same page boundaries.
+        assert_same_hits(buf.as_bytes(), 1..=buf.len(), |carver| {
+            carver.page_size(Some(8));
+        });
+    }
+
+    #[test]
+    fn test_min_score() {
+        let buf = "\x00\x00[0]\x00{\"id\": 7, \"tags\": [\"a\", \"b\"]}\x00[1, 2";
+        let mut carver = create_carver(buf.as_bytes());
+        carver.min_score = Some(30);
+        let mut report_writer = vec![];
+        let mut report = CsvReport::new(&mut report_writer, true);
+        carver.parse(&mut report).unwrap();
+        assert_eq!(
+            String::from_utf8(report_writer).unwrap(),
+            "completed,6,34,34,64\n"
+        );
+
+        // Every way of reading the stream gives the same scores, even for
+        // JSON strings that are far apart, or that are scanned again.
+        let mut buf = vec![];
+        for i in 0..256 {
+            buf.extend_from_slice(format!("{{\"n\": {i}, \"a\": [{i}, \"x\"]}}").as_bytes());
+            buf.extend(std::iter::repeat_n(b'\xaa' - (i % 3) as u8, i * 7 % 100));
+        }
+        assert_same_hits(&buf, [1, 7, 100, 4096, 5000], |carver| {
+            carver.min_score = Some(0);
+            carver.max_size(Some(24));
+            carver.salvage(true);
+        });
+    }
+
+    #[test]
+    fn test_structure_filters() {
+        let buf = r#"[1,2] {"a": 1, "b": [3, {"c": 4}]} [[5], [6], [7]] {"x": [1, 2, 3, 4]} {"d": 1, "e":"#;
+        let carve = |min_depth, min_members, top_level| {
+            let mut carver = create_carver(buf.as_bytes());
+            carver.min_depth = min_depth;
+            carver.min_members = min_members;
+            carver.top_level = top_level;
+            carver.salvage(true);
+            carver
+                .carve()
+                .map(|hit| hit.unwrap().start)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(carve(1, 0, None), [0, 6, 35, 51, 71]);
+        assert_eq!(carve(3, 0, None), [6]);
+        assert_eq!(carve(2, 3, None), [35]);
+        // Only the outermost object or array counts.
+        assert_eq!(carve(1, 2, Some(TopLevel::Object)), [6, 71]);
+        assert_eq!(carve(1, 0, Some(TopLevel::Array)), [0, 35]);
+
+        // Salvaged JSON strings are filtered by their own structure.
+        let buf = r#"[{"a": 1, "b": 2}, [1, 2, 3], x"#;
+        let hits = carve_with(buf.as_bytes(), |carver| {
+            carver.min_members = 3;
+            carver.salvage(true);
+        });
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].status, Status::Salvaged);
+        assert_eq!(&hits[0].data[..], b"[1, 2, 3]");
+
+        // Recovered suffixes are scanned again with the settings of the
+        // carver.
+        let buf = r#"x "a": [[1], 2]}"#;
+        let carve = |max_depth| {
+            let mut carver = create_carver(buf.as_bytes());
+            carver.min_depth = 3;
+            carver.max_depth(max_depth);
+            carver.recover_suffixes(Some(64));
+            carver
+                .carve()
+                .map(|hit| hit.unwrap().status)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(carve(3), [Status::SuffixRecovered]);
+        assert_eq!(carve(2), []);
+    }
+
+    #[test]
+    fn test_exhaustive() {
+        let buf = r#"{"a": [1, {"b": 2}], "c": "[3]"} [[4], x [5]"#;
+        let mut carver = create_carver(buf.as_bytes());
+        carver.exhaustive(true);
+        let mut json_writer = vec![];
+        let mut report_writer = vec![];
+        let mut report = CsvReport::new(&mut report_writer, true);
+        report.parents(true);
+        let mut sink = (JsonLines::new(&mut json_writer), report);
+        carver.parse(&mut sink).unwrap();
+        assert_eq!(
+            String::from_utf8(json_writer).unwrap(),
+            "{\"a\": [1, {\"b\": 2}], \"c\": \"[3]\"}\n\
+             [1, {\"b\": 2}]\n\
+             {\"b\": 2}\n\
+             [3]\n\
+             [4]\n\
+             [5]\n"
+        );
+        assert_eq!(
+            String::from_utf8(report_writer).unwrap(),
+            "completed,0,31,31,\n\
+             completed,6,18,18,0\n\
+             completed,10,17,17,0\n\
+             completed,27,29,29,0\n\
+             corrupted,33,38,36,\n\
+             completed,34,36,36,33\n\
+             completed,41,43,43,\n"
+        );
+
+        // Every way of reading the stream finds the same nested JSON strings.
+        assert_same_hits(buf.as_bytes(), 1..=buf.len(), |carver| {
+            carver.exhaustive(true);
+            carver.rank_repairs(Some(4));
+            // Spooled JSON strings are not ranked.
+            carver.spool_threshold = None;
+        });
+    }
+
+    #[test]
+    fn test_strings() {
+        let buf =
+            b"\x00\"hello\"\xff{\"a\": \"b\"}\"caf\\u00e9\" \"ab\" \"\xff\xfe\" \"x\x01\" \"tail";
+        let mut carver = create_carver(buf);
+        carver.strings(Some(3));
+        let mut json_writer = vec![];
+        let mut report_writer = vec![];
+        let mut json_lines = JsonLines::new(&mut json_writer);
+        json_lines.decode_strings(true);
+        let mut sink = (json_lines, CsvReport::new(&mut report_writer, true));
+        carver.parse(&mut sink).unwrap();
+        // Short, invalid and incomplete string literals are not reported.
+        assert_eq!(
+            String::from_utf8(json_writer).unwrap(),
+            "hello\n{\"a\": \"b\"}\ncafé\n"
+        );
+        assert_eq!(
+            String::from_utf8(report_writer).unwrap(),
+            "string,1,7,7\ncompleted,9,18,18\nstring,19,29,29\n"
+        );
+
+        // Decoded control characters are escaped again, so that every string
+        // literal takes a single line.
+        let mut carver = create_carver(br#""a\nb\u0001c" "d\te""#);
+        carver.strings(Some(3));
+        let mut json_writer = vec![];
+        let mut json_lines = JsonLines::new(&mut json_writer);
+        json_lines.decode_strings(true);
+        carver.parse(&mut json_lines).unwrap();
+        assert_eq!(
+            String::from_utf8(json_writer).unwrap(),
+            "a\\nb\\u0001c\nd\\te\n"
+        );
+
+        let buf = fs::read("tests/test_valid_but_no_brackets/y_structure_lonely_string.json");
+        let buf = buf.unwrap();
+        let hits = carve_with(&buf, |carver| carver.strings(Some(0)));
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].status, Status::String);
+        assert_eq!(&hits[0].data[..], br#""asd""#);
+
+        // A stray quote does not swallow the objects and arrays that follow
+        // it.
+        let buf = br#"x" {"key": "value"} y"#;
+        let mut carver = create_carver(buf);
+        carver.strings(Some(1));
+        let hits: Vec<(Status, usize, usize)> = carver
+            .carve()
+            .map(|hit| hit.map(|hit| (hit.status, hit.start, hit.end)).unwrap())
+            .collect();
+        assert_eq!(hits, [(Status::String, 1, 4), (Status::Completed, 3, 18)]);
+
+        // Every way of reading the stream finds the same string literals.
+        let buf = br#"x"a\"b" ["c", "d"] "eA" "f"#;
+        let expected = assert_same_hits(buf, 1..=buf.len(), |carver| {
+            carver.strings(Some(1));
+            carver.recover_suffixes(Some(8));
+        });
+        assert_eq!(expected.len(), 3);
+    }
+
+    #[test]
+    fn test_strict() {
+        let buf = b"[\"caf\xc3\xa9\", \"\\uD83D\\uDE00\"] {\"a\": \"\xc3\"} [\"\\uDE00\", 1] {\"b\": \"\xe0\x80\xaf\"} [\"\\uD83D\\n\"] [\"ok\"]";
+        let hits = carve_with(buf, |_| {});
+        assert!(hits.iter().all(|hit| hit.status == Status::Completed));
+
+        let mut carver = create_carver(buf);
+        carver.strict(true);
+        carver.fix_incomplete = true;
+        carver.repair_mode = RepairMode::Values;
+        let mut json_writer = vec![];
+        let mut report_writer = vec![];
+        let mut sink = (
+            JsonLines::new(&mut json_writer),
+            CsvReport::new(&mut report_writer, false),
+        );
+        carver.parse(&mut sink).unwrap();
+        // The invalid Unicode is dropped from the repaired JSON strings.
+        assert_eq!(
+            String::from_utf8(json_writer).unwrap(),
+            "[\"caf\u{e9}\", \"\\uD83D\\uDE00\"]\n{\"a\": \"\"}\n[\"\"]\n{\"b\": \"\"}\n[\"\"]\n[\"ok\"]\n"
+        );
+        assert_eq!(
+            String::from_utf8(report_writer).unwrap(),
+            "invalid_unicode,26,33,26\ninvalid_unicode,37,43,37\ninvalid_unicode,51,58,51\ninvalid_unicode,64,72,64\n"
+        );
+
+        // The byte that follows a lead byte is validated, even if it is ASCII.
+        let hits = carve_with(b"[\"\xc3abcdef\", 1]", |carver| carver.strict(true));
+        assert_eq!(hits[0].status, Status::InvalidUnicode);
+        assert_eq!(hits[0].end, 2);
+
+        // Raw control characters must be escaped, up to and including U+001F.
+        let hits = carve_with(b"[\"a\x1fb\"]", |carver| carver.strict(true));
+        assert_eq!(hits[0].status, Status::Corrupted);
+        assert_eq!(hits[0].end, 2);
+
+        // Every way of reading the stream finds the same JSON strings, even if
+        // it splits their characters and escape sequences.
+        assert_same_hits(buf, 1..=buf.len(), |carver| {
+            carver.strict(true);
+        });
+    }
+
+    #[test]
+    fn test_sanitize() {
+        let buf = b"{\"name\": \"caf\xe9\", \"emoji\": \"\\ud83d\xf0\x9f\x98\x80\"} [\"\xc3\xa9\", \"\xff\"] [\"a\xe2\x82";
+        let run = |buf: &[u8], mode, spool_threshold| {
+            let mut carver = Carver::new(BufReader::with_capacity(3, buf), None, None);
+            carver.min_size = 0;
+            carver.fix_incomplete = true;
+            carver.repair_mode = RepairMode::Values;
+            carver.spool_threshold = spool_threshold;
+            let mut json_lines = JsonLines::new(vec![]);
+            json_lines.sanitize(Some(mode));
+            let mut report = CsvReport::new(vec![], true);
+            report.sanitize(Some(mode));
+            let mut sink = (json_lines, report);
+            carver.parse(&mut sink).unwrap();
+            let json = String::from_utf8(sink.0.into_inner()).unwrap();
+            let report = String::from_utf8(sink.1.into_inner()).unwrap();
+            (json, report)
+        };
+        let (json, report) = run(buf, Sanitize::Replacement, None);
+        assert_eq!(
+            json,
+            "{\"name\": \"caf\\ufffd\", \"emoji\": \"\\ufffd\u{1f600}\"}\n[\"\u{e9}\", \"\\ufffd\"]\n[\"a\"]\n"
+        );
+        assert_eq!(
+            report,
+            "completed,0,38,38,2\ncompleted,40,50,50,1\nexhausted,52,56,52,0\n"
+        );
+        let (json, report) = run(buf, Sanitize::Latin1, None);
+        assert_eq!(
+            json,
+            "{\"name\": \"caf\\u00e9\", \"emoji\": \"\\ufffd\u{1f600}\"}\n[\"\u{e9}\", \"\\u00ff\"]\n[\"a\"]\n"
+        );
+        assert_eq!(
+            report,
+            "completed,0,38,38,2\ncompleted,40,50,50,1\nexhausted,52,56,52,0\n"
+        );
+
+        // Spooled bytes are sanitized as well, even if the spool file splits
+        // their sequences. Open strings are not repaired once spooled, though.
+        let buf = &buf[..51];
+        for mode in [Sanitize::Replacement, Sanitize::Latin1] {
+            let expected = run(buf, mode, None);
+            for threshold in 1..8 {
+                assert_eq!(run(buf, mode, Some(threshold)), expected);
+            }
+        }
+    }
 }
@@ -0,0 +1,90 @@
+//! Decode the string literals that the carver finds outside of arrays and
+//! objects.
+//!
+//! Binary data is full of text that is quoted, e.g., `"user@example.com"`,
+//! without being part of a larger JSON string. The state machine has already
+//! checked that the escape sequences of such a string literal are valid, so
+//! decoding it only has to check that it's valid UTF-8, and to replace its
+//! escape sequences with the characters that they stand for.
+
+use std::char::REPLACEMENT_CHARACTER;
+
+use crate::{
+    CHAR_ESC_BACKSPACE, CHAR_ESC_CARRIAGE_RETURN, CHAR_ESC_FORM_FEED, CHAR_ESC_LINE_FEED,
+    CHAR_ESC_TAB, CHAR_ESCAPE, CHAR_U,
+};
+
+/// Return the value of the four hex digits of a `\uXXXX` escape sequence.
+pub(crate) fn hex4(digits: &[u8]) -> Option<u32> {
+    let digits = std::str::from_utf8(digits.get(..4)?).ok()?;
+    u32::from_str_radix(digits, 16).ok()
+}
+
+/// Decode a string literal, quotes included, whose escape sequences are
+/// valid. Return `None` if it's not valid UTF-8.
+///
+/// Escaped surrogates that are not paired are replaced with U+FFFD, since
+/// they do not stand for a character.
+pub(crate) fn decode(literal: &[u8]) -> Option<String> {
+    let inner = &literal[1..literal.len() - 1];
+    let inner = std::str::from_utf8(inner).ok()?;
+    let mut decoded = String::with_capacity(inner.len());
+    let mut rest = inner;
+    while let Some(i) = rest.find(CHAR_ESCAPE as char) {
+        decoded.push_str(&rest[..i]);
+        let escape = &rest.as_bytes()[i + 1..];
+        let mut len = 2;
+        match escape[0] {
+            CHAR_ESC_BACKSPACE => decoded.push('\u{8}'),
+            CHAR_ESC_FORM_FEED => decoded.push('\u{c}'),
+            CHAR_ESC_LINE_FEED => decoded.push('\n'),
+            CHAR_ESC_CARRIAGE_RETURN => decoded.push('\r'),
+            CHAR_ESC_TAB => decoded.push('\t'),
+            CHAR_U => {
+                len = 6;
+                let unit = hex4(&escape[1..])?;
+                let low = match escape.get(5..7) {
+                    Some(b"\\u") => hex4(&escape[7..]),
+                    _ => None,
+                };
+                let c = match (unit, low) {
+                    (0xD800..=0xDBFF, Some(low @ 0xDC00..=0xDFFF)) => {
+                        len = 12;
+                        char::from_u32(0x10000 + ((unit - 0xD800) << 10) + (low - 0xDC00))
+                    }
+                    _ => char::from_u32(unit),
+                };
+                decoded.push(c.unwrap_or(REPLACEMENT_CHARACTER));
+            }
+            // A quote, a backslash, or a slash stands for itself.
+            b => decoded.push(b as char),
+        }
+        rest = &rest[i + len..];
+    }
+    decoded.push_str(rest);
+    Some(decoded)
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use super::*;
+
+    #[rstest]
+    #[case(br#""""#, Some(""))]
+    #[case(br#""hello""#, Some("hello"))]
+    #[case(br#""a\"b\\c\/d""#, Some("a\"b\\c/d"))]
+    #[case(br#""\b\f\n\r\t""#, Some("\u{8}\u{c}\n\r\t"))]
+    #[case("\"café\"".as_bytes(), Some("café"))]
+    #[case("\"καλημέρα\"".as_bytes(), Some("καλημέρα"))]
+    #[case(br#""caf\u00e9""#, Some("café"))]
+    #[case(br#""\ud83d\ude00!""#, Some("😀!"))]
+    #[case(br#""\ud83d!""#, Some("\u{fffd}!"))]
+    #[case(br#""\ude00\ud83d""#, Some("\u{fffd}\u{fffd}"))]
+    #[case(b"\"caf\xc3\"", None)]
+    #[case(b"\"\xff\"", None)]
+    fn test_decode(#[case] literal: &[u8], #[case] expected: Option<&str>) {
+        assert_eq!(decode(literal).as_deref(), expected);
+    }
+}
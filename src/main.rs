@@ -1,9 +1,47 @@
 use std::fs::File;
+use std::io;
 use std::path;
 
-use json_carver::{Carver, Reader, Writer, DEFAULT_MIN_JSON_SIZE};
+use json_carver::{
+    Carver, CsvReport, DEFAULT_MAX_IDENT_DEPTH, DEFAULT_MIN_JSON_SIZE, DEFAULT_REPAIR_LOOKAHEAD,
+    DEFAULT_SUFFIX_WINDOW, Encoding, JsonLines, PageSearch, Reader, Reassembler, RepairLog,
+    RepairMode, Sanitize, Sink, TopLevel, Writer,
+};
 
-use clap::Parser;
+use clap::{Parser, ValueEnum};
+
+/// How to fix incomplete JSON strings (see [`RepairMode`]).
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum Repair {
+    Brackets,
+    Values,
+}
+
+/// The type of the outermost value of JSON strings (see [`TopLevel`]).
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum Kind {
+    Object,
+    Array,
+}
+
+/// How to replace invalid Unicode within strings (see [`Sanitize`]).
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum Replace {
+    Replacement,
+    Latin1,
+}
+
+/// The encoding of JSON strings (see [`Encoding`]).
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum Charset {
+    Utf8,
+    Utf16le,
+    Utf16be,
+    Utf32le,
+    Utf32be,
+    Latin1,
+    Auto,
+}
 
 /// Find JSON strings in a file faster than strings(1), print structurally
 /// valid ones and report corrupted ones.
@@ -14,6 +52,30 @@ struct Args {
     #[arg(short, long)]
     input: Option<path::PathBuf>,
 
+    /// Memory-map the input file instead of reading it. This is faster for
+    /// large files.
+    #[arg(long, default_value_t = false, requires = "input")]
+    mmap: bool,
+
+    /// Carve the input file with this many threads. Implies --mmap.
+    #[arg(long, default_value_t = 1, requires = "input")]
+    threads: usize,
+
+    /// The encoding of the JSON strings in the input file. JSON strings in
+    /// other encodings than UTF-8 are written in UTF-8, and their encoding is
+    /// appended to every report. "auto" detects the encoding of every JSON
+    /// string. Implies --mmap.
+    #[arg(long, value_enum, default_value_t = Charset::Utf8, requires = "input", conflicts_with = "page_size")]
+    encoding: Charset,
+
+    /// Parse the input file as a Java heap dump (HPROF), and carve only the
+    /// strings and the byte and char arrays in it, each in its own encoding.
+    /// The ID and the class of the object that JSON strings were found in
+    /// are appended to every report. Malformed records are skipped with a
+    /// warning. Implies --mmap.
+    #[arg(long, default_value_t = false, requires = "input", conflicts_with_all = ["encoding", "page_size", "threads"])]
+    hprof: bool,
+
     /// Where to write the JSON strings. Writes to stdout by default.
     #[arg(short, long)]
     output: Option<path::PathBuf>,
@@ -26,24 +88,149 @@ struct Args {
     #[arg(long, default_value_t = false)]
     replace_newlines: bool,
 
+    /// Maximum nesting depth of JSON strings. Deeper ones are reported as
+    /// "too_deep".
+    #[arg(long, default_value_t = DEFAULT_MAX_IDENT_DEPTH, value_name = "DEPTH")]
+    max_depth: usize,
+
+    /// Maximum size of JSON strings. Larger ones are reported as "too_large",
+    /// and the carver looks for smaller JSON strings within them.
+    #[arg(long, value_name = "BYTES")]
+    max_size: Option<usize>,
+
+    /// Report the complete JSON strings that are nested within corrupted ones
+    /// as "salvaged".
+    #[arg(long, default_value_t = false)]
+    salvage: bool,
+
+    /// Scan every JSON string again, right after its first byte, and report
+    /// the complete JSON strings that start within other ones as well. The
+    /// position of the JSON string that they are nested within is appended to
+    /// every report.
+    #[arg(long, default_value_t = false)]
+    exhaustive: bool,
+
+    /// Recover the ends of JSON strings whose beginning was overwritten, by
+    /// scanning backwards from unmatched closing brackets. They are reported
+    /// as "suffix_recovered".
+    #[arg(long, default_value_t = false)]
+    recover_suffixes: bool,
+
+    /// Stitch the beginnings of corrupted JSON strings to the recovered ends
+    /// of other ones, if they are compatible, and write them to this file,
+    /// along with the parts of the byte stream that they came from. Implies
+    /// --recover-suffixes.
+    #[arg(long, value_name = "FILE")]
+    reassemble: Option<path::PathBuf>,
+
+    /// Carve the quoted strings outside of JSON arrays and objects as well, if
+    /// they have at least this many characters. They are reported as
+    /// "string".
+    #[arg(long, value_name = "MIN_LEN")]
+    strings: Option<usize>,
+
+    /// Write the quoted strings decoded, without their quotes and escape
+    /// sequences, like strings(1) would. Control characters stay escaped.
+    #[arg(long, default_value_t = false, requires = "strings")]
+    decode_strings: bool,
+
+    /// Reject strings that are not valid UTF-8, or that have unpaired
+    /// surrogate escapes, as RFC 8259 does. The JSON strings that contain them
+    /// are reported as "invalid_unicode".
+    #[arg(long, default_value_t = false)]
+    strict: bool,
+
+    /// Replace the invalid UTF-8 sequences within the strings of the JSON
+    /// strings that are written with "\ufffd", or reinterpret their bytes as
+    /// Latin-1, e.g., "\u00e9". Unpaired surrogate escapes are replaced with
+    /// "\ufffd". The number of replacements is appended to every report.
+    #[arg(long, value_enum, value_name = "MODE", conflicts_with = "strict")]
+    sanitize: Option<Replace>,
+
     /// Minimum size of JSON strings to report.
     #[arg(long, default_value_t = DEFAULT_MIN_JSON_SIZE)]
     min_size: usize,
 
+    /// Score how likely it is that JSON strings are not false positives, from
+    /// 0 to 100, and report only the ones that score at least this much. The
+    /// score is appended to every report.
+    #[arg(long, value_name = "SCORE", value_parser = clap::value_parser!(u8).range(0..=100))]
+    min_score: Option<u8>,
+
+    /// Minimum nesting depth of JSON strings to report.
+    #[arg(long, default_value_t = 1, value_name = "DEPTH")]
+    min_depth: usize,
+
+    /// Minimum number of keys of objects, or of elements of arrays, to
+    /// report. Only the outermost object or array of a JSON string counts.
+    #[arg(long, default_value_t = 0, value_name = "COUNT")]
+    min_members: usize,
+
+    /// Report only JSON strings whose outermost value is of this type.
+    #[arg(long, value_enum, value_name = "TYPE")]
+    top_level: Option<Kind>,
+
     /// Attempt to fix incomplete JSON strings by returning an incomplete, but
     /// structurally valid, version of them.
     #[arg(long, default_value_t = false)]
     fix_incomplete: bool,
 
+    /// How to fix incomplete JSON strings: truncate them right after their
+    /// last bracket, or right after their last complete value.
+    #[arg(long, value_enum, default_value_t = Repair::Brackets, value_name = "MODE")]
+    repair: Repair,
+
+    /// Write the ranked ways to repair incomplete JSON strings to this file,
+    /// along with the edits that each one makes to the byte stream.
+    #[arg(long, value_name = "FILE")]
+    repair_log: Option<path::PathBuf>,
+
+    /// Treat the input as a memory image with pages of this size. JSON
+    /// strings that are corrupted right at a page boundary are reported as
+    /// "page_boundary", and every report lists the page boundaries that the
+    /// JSON string crosses.
+    #[arg(long, value_name = "BYTES")]
+    page_size: Option<usize>,
+
+    /// Search the other pages of the input file for the continuations of
+    /// JSON strings that were corrupted at a page boundary, and write the
+    /// completed JSON strings to this file. Implies --mmap.
+    #[arg(long, value_name = "FILE", requires_all = ["page_size", "input"])]
+    page_search: Option<path::PathBuf>,
+
     /// Report every JSON string in the stream, not just corrupted ones.
     #[arg(long, default_value_t = false)]
     report_all: bool,
+
+    /// Move JSON strings to a temporary file once they take up this many
+    /// bytes in memory. Keeps memory usage bounded for huge JSON strings.
+    #[arg(long, value_name = "BYTES")]
+    spool_threshold: Option<usize>,
+}
+
+/// A sink that warns about the parts of the input file that were skipped.
+struct Warnings;
+
+impl Sink for Warnings {
+    fn on_malformed(&mut self, pos: usize, reason: &str) -> io::Result<()> {
+        eprintln!("Skipped a malformed part of the input file at position {pos}: {reason}");
+        Ok(())
+    }
 }
 
 fn main() {
     let args = Args::parse();
     let reader = match args.input {
         None => Reader::from_stdin(),
+        Some(p)
+            if args.mmap
+                || args.threads > 1
+                || args.encoding != Charset::Utf8
+                || args.hprof
+                || args.page_search.is_some() =>
+        {
+            Reader::from_mmap(&File::open(&p).unwrap()).unwrap()
+        }
         Some(p) => Reader::from_file(File::open(&p).unwrap(), None),
     };
     let json_writer = match args.output {
@@ -54,14 +241,101 @@ fn main() {
         None => Writer::to_stderr(),
         Some(p) => Writer::to_file(File::create(&p).unwrap(), None),
     };
-    let mut carver = Carver::new(reader, json_writer, report_writer, None, None);
+    let mut report = CsvReport::new(report_writer, args.report_all);
+    report.page_size(args.page_size);
+    report.parents(args.exhaustive);
+    let sanitize = args.sanitize.map(|mode| match mode {
+        Replace::Replacement => Sanitize::Replacement,
+        Replace::Latin1 => Sanitize::Latin1,
+    });
+    report.sanitize(sanitize);
+    let mut json_lines = JsonLines::new(json_writer);
+    json_lines.decode_strings(args.decode_strings);
+    json_lines.sanitize(sanitize);
+    let mut carver = Carver::new(reader, None, None);
     carver.min_size = args.min_size;
+    carver.min_score = args.min_score;
+    carver.min_depth = args.min_depth;
+    carver.min_members = args.min_members;
+    carver.top_level = args.top_level.map(|kind| match kind {
+        Kind::Object => TopLevel::Object,
+        Kind::Array => TopLevel::Array,
+    });
     carver.fix_incomplete = args.fix_incomplete;
-    carver.report_all = args.report_all;
+    carver.repair_mode = match args.repair {
+        Repair::Brackets => RepairMode::Brackets,
+        Repair::Values => RepairMode::Values,
+    };
+    carver.spool_threshold = args.spool_threshold;
     carver.replace_newlines(args.replace_newlines);
-    match carver.parse() {
-        Ok(_) => std::process::exit(0),
-        Err(e) => {
+    carver.max_depth(args.max_depth);
+    carver.max_size(args.max_size);
+    carver.salvage(args.salvage);
+    carver.exhaustive(args.exhaustive);
+    carver.strings(args.strings);
+    carver.strict(args.strict);
+    carver.page_size(args.page_size);
+    if args.recover_suffixes || args.reassemble.is_some() {
+        carver.recover_suffixes(Some(DEFAULT_SUFFIX_WINDOW));
+    }
+    if args.repair_log.is_some() {
+        carver.rank_repairs(Some(DEFAULT_REPAIR_LOOKAHEAD));
+    }
+    let sink = (json_lines, report);
+    let mut reassembler = Reassembler::new(&carver);
+    let mut page_search = PageSearch::new(&carver);
+    let mut sink: Box<dyn Sink + '_> = match &args.repair_log {
+        None => Box::new(sink),
+        Some(p) => {
+            let log_writer = Writer::to_file(File::create(p).unwrap(), None);
+            Box::new((sink, RepairLog::new(log_writer)))
+        }
+    };
+    if args.reassemble.is_some() {
+        sink = Box::new((sink, &mut reassembler));
+    }
+    if args.page_search.is_some() {
+        sink = Box::new((sink, &mut page_search));
+    }
+    if args.hprof {
+        sink = Box::new((sink, Warnings));
+    }
+    let encodings = match args.encoding {
+        Charset::Utf8 => &[Encoding::Utf8][..],
+        Charset::Utf16le => &[Encoding::Utf16Le],
+        Charset::Utf16be => &[Encoding::Utf16Be],
+        Charset::Utf32le => &[Encoding::Utf32Le],
+        Charset::Utf32be => &[Encoding::Utf32Be],
+        Charset::Latin1 => &[Encoding::Latin1],
+        Charset::Auto => Encoding::ALL,
+    };
+    let parallel = args.threads > 1 || args.encoding != Charset::Utf8;
+    if let (Reader::Mmap(input), true) = (carver.get_ref(), args.hprof) {
+        if let Err(e) = carver.parse_hprof(input.get_ref(), &mut *sink) {
+            eprintln!("Encountered an error: {:?}", e);
+            std::process::exit(1)
+        }
+    } else if let (Reader::Mmap(input), true) = (carver.get_ref(), parallel) {
+        let input = input.get_ref();
+        if let Err(e) = carver.parse_encoded(input, encodings, args.threads, &mut *sink) {
+            eprintln!("Encountered an error: {:?}", e);
+            std::process::exit(1)
+        }
+    } else if let Err(e) = carver.parse(&mut *sink) {
+        eprintln!("Encountered an error: {:?}", e);
+        std::process::exit(1)
+    }
+    drop(sink);
+    if let Some(p) = &args.reassemble {
+        let log_writer = Writer::to_file(File::create(p).unwrap(), None);
+        if let Err(e) = reassembler.write_log(log_writer) {
+            eprintln!("Encountered an error: {:?}", e);
+            std::process::exit(1)
+        }
+    }
+    if let (Some(p), Reader::Mmap(input)) = (&args.page_search, carver.get_ref()) {
+        let log_writer = Writer::to_file(File::create(p).unwrap(), None);
+        if let Err(e) = page_search.write_log(input.get_ref(), log_writer) {
             eprintln!("Encountered an error: {:?}", e);
             std::process::exit(1)
         }
@@ -0,0 +1,252 @@
+//! Search the pages of a memory image for the continuations of JSON strings.
+//!
+//! In a raw physical memory dump, the pages that are adjacent in the dump are
+//! rarely adjacent in the virtual memory of a process. A JSON string that
+//! crosses a page boundary is then interrupted by the unrelated data of the
+//! next page, and its continuation, if it's still in memory, lies in some
+//! other page. We scan the JSON string again, to get the state of the parser
+//! at the boundary, and feed it with the first bytes of every other page,
+//! until it completes.
+
+use std::collections::HashMap;
+use std::io;
+use std::io::Write;
+
+use crate::sink::write_json_string;
+use crate::{Carver, Cause, Hit, JsonTracker, Sink};
+
+/// A JSON string that was interrupted at a page boundary.
+#[derive(Debug)]
+struct Cut {
+    start: usize,
+    boundary: usize,
+    // The state of the parser at the boundary.
+    jt: JsonTracker,
+    data: Vec<u8>,
+}
+
+/// A JSON string that was interrupted at a page boundary, and was completed
+/// with the first bytes of another page.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Continuation {
+    /// The position of the first character of the JSON string within the
+    /// byte stream.
+    pub start: usize,
+    /// The page boundary that interrupted the JSON string.
+    pub boundary: usize,
+    /// The position of the first byte of the page that continues it.
+    pub page: usize,
+    /// The position of the last byte of the continuation within the byte
+    /// stream.
+    pub end: usize,
+    /// The completed JSON string, i.e., the bytes up to the boundary and the
+    /// bytes of the continuation.
+    pub data: Vec<u8>,
+}
+
+/// A sink that collects the JSON strings that were interrupted at a page
+/// boundary (see [`crate::Carver::page_size`]), so that their continuations
+/// can be searched for once the byte stream has been carved.
+///
+/// The JSON strings are kept in memory until the search, along with the
+/// state of the parser at their boundary, so the memory that the sink takes
+/// up grows with the number of JSON strings that page boundaries interrupt.
+/// Every one of them is at most [`Carver::max_size`] bytes long.
+#[derive(Debug)]
+pub struct PageSearch {
+    page_size: usize,
+    // A parser with the settings of the carver, for scanning the JSON
+    // strings again.
+    rescanner: JsonTracker,
+    cuts: Vec<Cut>,
+}
+
+impl PageSearch {
+    /// Create a sink that collects the JSON strings that `carver` interrupts
+    /// at page boundaries (see [`Carver::page_size`]), and completes them
+    /// with the same settings.
+    pub fn new<R>(carver: &Carver<R>) -> Self {
+        PageSearch {
+            page_size: carver.page_size.unwrap_or(1).max(1),
+            rescanner: carver.rescanner(),
+            cuts: vec![],
+        }
+    }
+
+    /// Return the continuations of the collected JSON strings within
+    /// `input`, the byte stream that was carved, in the order of their
+    /// positions.
+    ///
+    /// A continuation must complete the JSON string within the page that it
+    /// starts. Every JSON string gets the continuation in the nearest page
+    /// that follows it, or, if there is none, in the nearest page that
+    /// precedes it. The pages that the JSON string spans are skipped, and so
+    /// is the page that interrupted it.
+    pub fn search(&self, input: &[u8]) -> Vec<Continuation> {
+        let pages = input.len().div_ceil(self.page_size);
+        let mut by_first_byte = vec![vec![]; 256];
+        for page in 0..pages {
+            by_first_byte[input[page * self.page_size] as usize].push(page);
+        }
+        // The first bytes that the parser accepts, for every state that it
+        // was left in at a boundary.
+        let mut accepted = HashMap::new();
+        let mut continuations = vec![];
+        for cut in &self.cuts {
+            let first = cut.start / self.page_size;
+            let next = cut.boundary / self.page_size;
+            // Only the pages whose first byte the parser accepts at the
+            // boundary can continue the JSON string.
+            let bytes = accepted.entry(cut.jt.expecting()).or_insert_with(|| {
+                (0..=u8::MAX)
+                    .filter(|&b| {
+                        matches!(
+                            cut.jt.clone().feed(&[b]),
+                            (_, None | Some(Cause::Completed))
+                        )
+                    })
+                    .collect::<Vec<u8>>()
+            });
+            let mut candidates: Vec<usize> = bytes
+                .iter()
+                .flat_map(|&b| by_first_byte[b as usize].iter().copied())
+                .filter(|&page| page > next || page < first)
+                .collect();
+            candidates.sort_unstable_by_key(|&page| (page < first, page.abs_diff(next)));
+            for page in candidates.into_iter().map(|page| page * self.page_size) {
+                let bytes = &input[page..input.len().min(page + self.page_size)];
+                let mut jt = cut.jt.clone();
+                if let (used, Some(Cause::Completed)) = jt.feed(bytes) {
+                    continuations.push(Continuation {
+                        start: cut.start,
+                        boundary: cut.boundary,
+                        page,
+                        end: page + used - 1,
+                        data: [&cut.data[..], &bytes[..used]].concat(),
+                    });
+                    break;
+                }
+            }
+        }
+        continuations
+    }
+
+    /// Search for the continuations of the collected JSON strings within
+    /// `input`, and write them to `writer`, one per line.
+    ///
+    /// Every line is a JSON object with the following fields:
+    ///
+    /// ```text
+    /// {"start":4090,"boundary":4096,"page":12288,"end":12291,"continued":"{\"a\": [1, 2]}"}
+    /// ```
+    ///
+    /// where:
+    /// * `start` is the position of the JSON string in the byte stream.
+    /// * `boundary` is the page boundary that interrupted it.
+    /// * (`page`, `end`) is the position of its continuation in the byte
+    ///   stream, last byte included.
+    /// * `continued` is the completed JSON string, quoted and escaped.
+    pub fn write_log<W: Write>(&self, input: &[u8], mut writer: W) -> io::Result<()> {
+        for continuation in self.search(input) {
+            write!(
+                writer,
+                "{{\"start\":{},\"boundary\":{},\"page\":{},\"end\":{},\"continued\":",
+                continuation.start, continuation.boundary, continuation.page, continuation.end,
+            )?;
+            write_json_string(&mut writer, &continuation.data)?;
+            writer.write_all(b"}\n")?;
+        }
+        writer.flush()
+    }
+}
+
+impl Sink for PageSearch {
+    fn on_page_boundary(&mut self, hit: &Hit) -> io::Result<()> {
+        let data = match &hit.spooled {
+            None => hit.data.to_vec(),
+            Some(_) => hit.clone().into_owned()?.data.into_owned(),
+        };
+        if let (jt, None) = self.rescanner.rescan(&data) {
+            self.cuts.push(Cut {
+                start: hit.start,
+                boundary: hit.end + 1,
+                jt,
+                data,
+            });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use crate::DEFAULT_MAX_IDENT_DEPTH;
+    use crate::tests::create_carver;
+
+    use super::*;
+
+    fn search(buf: &[u8], max_depth: usize) -> Vec<Continuation> {
+        let mut carver = create_carver(buf);
+        carver.page_size(Some(8));
+        carver.max_depth(max_depth);
+        let mut search = PageSearch::new(&carver);
+        carver.parse(&mut search).unwrap();
+        search.search(buf)
+    }
+
+    #[rstest]
+    // The continuation follows the JSON string.
+    #[case(
+        b"xx{\"a\": \x00\x00\x00\x00\x00\x00\x00\x00[1]}\x00",
+        vec![(2, 8, 16, 19, r#"{"a": [1]}"#)]
+    )]
+    // The pages that cannot continue the JSON string are skipped.
+    #[case(
+        b"xx{\"a\": \x00\x00\x00\x00\x00\x00\x00\x00]}\x00\x00\x00\x00\x00\x00[1]}\x00",
+        vec![(2, 8, 24, 27, r#"{"a": [1]}"#)]
+    )]
+    // The continuation precedes the JSON string.
+    #[case(b"\"b\"}]xxx[{\"a\":  \x00\x00", vec![(8, 16, 0, 4, r#"[{"a":  "b"}]"#)])]
+    // The continuation does not match the state of the parser.
+    #[case(b"xx{\"a\": \x00\x00\x00\x00\x00\x00\x00\x00]}\x00", vec![])]
+    // The JSON string was not interrupted at a page boundary.
+    #[case(b"x{\"a\": \x00\x00\x00\x00\x00\x00\x00\x00\x00[1]}", vec![])]
+    fn test_search(#[case] buf: &[u8], #[case] expected: Vec<(usize, usize, usize, usize, &str)>) {
+        let expected: Vec<Continuation> = expected
+            .into_iter()
+            .map(|(start, boundary, page, end, data)| Continuation {
+                start,
+                boundary,
+                page,
+                end,
+                data: data.as_bytes().to_vec(),
+            })
+            .collect();
+        assert_eq!(search(buf, DEFAULT_MAX_IDENT_DEPTH), expected);
+    }
+
+    #[test]
+    fn test_search_settings() {
+        // The continuation is too deep for the carver.
+        let buf = b"xx{\"a\": \x00\x00\x00\x00\x00\x00\x00\x00[1]}\x00";
+        assert_eq!(search(buf, 2).len(), 1);
+        assert_eq!(search(buf, 1), vec![]);
+    }
+
+    #[test]
+    fn test_write_log() {
+        let buf = b"xx{\"a\":\n\x00\x00\x00\x00\x00\x00\x00\x00[1]}\x00";
+        let mut carver = create_carver(buf);
+        carver.page_size(Some(8));
+        let mut search = PageSearch::new(&carver);
+        carver.parse(&mut search).unwrap();
+        let mut log = vec![];
+        search.write_log(buf, &mut log).unwrap();
+        assert_eq!(
+            String::from_utf8(log).unwrap(),
+            "{\"start\":2,\"boundary\":8,\"page\":16,\"end\":19,\"continued\":\"{\\\"a\\\":\\n[1]}\"}\n"
+        );
+    }
+}
@@ -0,0 +1,372 @@
+//! Carve a buffer with multiple threads.
+//!
+//! The buffer is split into chunks, and each chunk is carved by a different
+//! thread. A thread starts JSON strings only within its chunk, but may
+//! continue past its end, until the JSON string stops.
+//!
+//! A thread does not know where the JSON string of the previous chunk has
+//! stopped, so the start of its chunk may be in the middle of a JSON string.
+//! For this reason, the results of the threads are merged in order, and the
+//! results that a sequential carver would not produce are dropped. If the
+//! previous chunk has stopped in the middle of a JSON string that the thread
+//! has found, the part of the chunk where the two disagree is carved again.
+
+use std::io;
+use std::ops::Range;
+use std::thread;
+
+use crate::{Carver, Cause, Options, Resolved, Sink, State};
+
+/// The size of the chunks that each thread carves.
+const PARALLEL_CHUNK_SIZE: usize = 16 << 20; // 16MiB
+
+/// A JSON string that was found in the buffer, regardless of whether it
+/// should be reported or not.
+struct Span<'a> {
+    /// The position where the JSON string starts.
+    start: usize,
+    /// The position where the carver resumes scouting, after the JSON string
+    /// has stopped.
+    resume: usize,
+    /// The last JSON string that did not start within another one, before
+    /// and after this one has stopped, if the carver carves exhaustively.
+    outer: (Outer, Outer),
+    /// The position before which quotes do not start string literals, before
+    /// and after this one has stopped.
+    quotes_from: (usize, usize),
+    hits: Resolved<'a>,
+}
+
+/// The position of the last JSON string that did not start within another
+/// one, as (start, end).
+type Outer = Option<(usize, usize)>;
+
+/// Return the position of the JSON string that the one that starts at
+/// `start` would be nested within, given the `outer` one.
+fn parent(outer: Outer, start: usize) -> Option<usize> {
+    outer
+        .filter(|&(outer_start, outer_end)| outer_start < start && start <= outer_end)
+        .map(|(outer_start, _)| outer_start)
+}
+
+/// Carve the JSON strings that start within a range of a buffer.
+///
+/// The whole buffer is available to the carver, so the hits borrow it instead
+/// of copying it.
+struct Scan<'a> {
+    carver: Carver<io::Empty>,
+    opts: Options,
+    input: &'a [u8],
+    pos: usize,
+    end: usize,
+}
+
+impl<'a> Scan<'a> {
+    fn new(
+        template: &Carver<io::Empty>,
+        input: &'a [u8],
+        range: Range<usize>,
+        (outer, quotes_from): (Outer, usize),
+    ) -> Self {
+        let mut carver = template.fork();
+        carver.jt.pos = range.start;
+        carver.jt.outer = outer;
+        carver.jt.quotes_from = quotes_from;
+        Scan {
+            opts: carver.options(),
+            carver,
+            input,
+            pos: range.start,
+            end: range.end,
+        }
+    }
+}
+
+impl<'a> Scan<'a> {
+    /// Create the hits for the JSON string that has just stopped. The whole
+    /// buffer up to the current position is available to the carver, both for
+    /// borrowing and for looking back.
+    fn resolve(&mut self, cause: Cause) -> Resolved<'a> {
+        let consumed = &self.input[..self.pos];
+        match cause {
+            Cause::Suffix => self.carver.jt.recover_suffix(consumed, &self.opts),
+            cause => self
+                .carver
+                .jt
+                .resolve(cause, consumed, None, consumed, &self.opts),
+        }
+    }
+}
+
+impl<'a> Iterator for Scan<'a> {
+    type Item = Span<'a>;
+
+    fn next(&mut self) -> Option<Span<'a>> {
+        loop {
+            // JSON strings that have started within the range can go past
+            // its end.
+            let end = match self.carver.jt.in_progress() {
+                true => self.input.len(),
+                false => self.end,
+            };
+            if self.pos >= end {
+                if !self.carver.jt.in_progress() {
+                    return None;
+                }
+                let cause = match (self.carver.jt.state, self.carver.jt.corrupted_at) {
+                    (State::Orphan, _) => Cause::Suffix,
+                    (_, Some(_)) => Cause::Corrupted,
+                    _ => Cause::Exhausted,
+                };
+                let start = self.carver.jt.start;
+                let outer = self.carver.jt.outer;
+                let quotes_from = self.carver.jt.quotes_from;
+                let hits = self.resolve(cause);
+                // The bytes that the carver has looked ahead at are scanned
+                // again.
+                self.pos = self.carver.jt.pos;
+                return Some(Span {
+                    start,
+                    resume: self.pos,
+                    outer: (outer, self.carver.jt.outer),
+                    quotes_from: (quotes_from, self.carver.jt.quotes_from),
+                    hits,
+                });
+            }
+
+            let (used, cause) = self.carver.jt.feed(&self.input[self.pos..end]);
+            self.pos += used;
+            if let Some(cause) = cause {
+                let start = self.carver.jt.start;
+                let outer = self.carver.jt.outer;
+                let quotes_from = self.carver.jt.quotes_from;
+                let hits = self.resolve(cause);
+                // JSON strings that are too large are scanned again, right
+                // after their start, and so are the bytes that the carver
+                // has looked ahead at.
+                self.pos = self.carver.jt.pos;
+                return Some(Span {
+                    start,
+                    resume: self.pos,
+                    outer: (outer, self.carver.jt.outer),
+                    quotes_from: (quotes_from, self.carver.jt.quotes_from),
+                    hits,
+                });
+            }
+        }
+    }
+}
+
+/// Whether a sequential carver that scouts from `pos` would be in sync with
+/// the `spans` of a thread, i.e., whether the thread was not in the middle
+/// of a JSON string at `pos`, and it would find the JSON strings that start
+/// within other ones at the same positions.
+///
+/// `outer` is the last JSON string of the sequential carver that did not
+/// start within another one, and `quotes_from` is the position before which
+/// its quotes do not start string literals.
+fn in_sync(spans: &[Span<'_>], pos: usize, (outer, quotes_from): (Outer, usize)) -> bool {
+    let i = spans.partition_point(|span| span.start < pos);
+    let nesting = spans
+        .get(i)
+        .is_none_or(|span| parent(span.outer.0, span.start) == parent(outer, span.start));
+    // The thread skips the same quotes as the sequential carver, both before
+    // its next JSON string and after its last one.
+    let skipped = match spans.get(i) {
+        Some(span) => span.quotes_from.0,
+        None => spans.last().map_or(0, |span| span.quotes_from.1),
+    };
+    (i == 0 || spans[i - 1].resume <= pos) && nesting && skipped.max(pos) == quotes_from.max(pos)
+}
+
+impl<R> Carver<R> {
+    /// Carve a buffer for JSON strings with multiple threads, and pass them
+    /// to `sink`.
+    ///
+    /// This produces the exact same results as carving the buffer with a
+    /// single thread. It's meant for large, memory-mapped files (see
+    /// [`crate::Reader::from_mmap`]).
+    pub fn parse_parallel<S: Sink + ?Sized>(
+        &self,
+        input: &[u8],
+        threads: usize,
+        sink: &mut S,
+    ) -> io::Result<()> {
+        self.parse_chunks(input, threads, PARALLEL_CHUNK_SIZE, sink)
+    }
+
+    fn parse_chunks<S: Sink + ?Sized>(
+        &self,
+        input: &[u8],
+        threads: usize,
+        chunk_size: usize,
+        sink: &mut S,
+    ) -> io::Result<()> {
+        let chunks: Vec<Range<usize>> = (0..input.len())
+            .step_by(chunk_size)
+            .map(|start| start..input.len().min(start + chunk_size))
+            .collect();
+        let template = self.fork();
+        // The position where a sequential carver would scout from, its last
+        // JSON string that did not start within another one, and the position
+        // before which its quotes do not start string literals.
+        let mut frontier = 0;
+        let mut scouting = (None, 0);
+
+        for batch in chunks.chunks(threads.max(1)) {
+            let results: Vec<Vec<Span>> = thread::scope(|scope| {
+                let handles: Vec<_> = batch
+                    .iter()
+                    .map(|range| {
+                        scope.spawn(|| {
+                            Scan::new(&template, input, range.clone(), (None, 0)).collect()
+                        })
+                    })
+                    .collect();
+                handles
+                    .into_iter()
+                    .map(|handle| handle.join().unwrap())
+                    .collect()
+            });
+
+            for (range, spans) in batch.iter().zip(results) {
+                if !in_sync(&spans, frontier, scouting) {
+                    // Carve sequentially, until we get in sync with the
+                    // thread again.
+                    let range = frontier..range.end.max(frontier);
+                    let end = range.end;
+                    let mut synced = false;
+                    for span in Scan::new(&template, input, range, scouting) {
+                        frontier = span.resume;
+                        scouting = (span.outer.1, span.quotes_from.1);
+                        for hit in span.hits.iter() {
+                            sink.on_hit(hit)?;
+                        }
+                        if in_sync(&spans, frontier, scouting) {
+                            synced = true;
+                            break;
+                        }
+                    }
+                    if !synced {
+                        // Nothing else starts in this chunk, so none of the
+                        // thread's spans belong to the output.
+                        frontier = frontier.max(end);
+                    }
+                }
+                let from = spans.partition_point(|span| span.start < frontier);
+                for span in &spans[from..] {
+                    frontier = span.resume;
+                    scouting = (span.outer.1, span.quotes_from.1);
+                    for hit in span.hits.iter() {
+                        sink.on_hit(hit)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Hit;
+
+    #[derive(Default)]
+    struct Hits(Vec<Hit<'static>>);
+
+    impl Sink for Hits {
+        fn on_hit(&mut self, hit: &Hit) -> io::Result<()> {
+            self.0.push(hit.clone().into_owned()?);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_parse_chunks() {
+        let bufs = [
+            r#"[1]{"key":"val":  [2],[fal[3]]]{"a""#,
+            r#"{"a": "[1, 2]", "b": {"c": [{}, {"d": "}{"}]}}[[[{"e": 1}"#,
+            r#"[1, 2, 3, {"test"[true, null, far{"key": "value",[9]"#,
+            r#"["[", "{", "[{", ["]", "}"], {"[": "{"}] [] {} ["#,
+            r#"xx"body": "text"}, {"id": 5}]} ] 1, 2]]], "a": [3]}"#,
+            r#"["\uD83D\uDE00", {"a": "\uDE00x"}, ["\uD83D"]] ["é" "\uD83D\n"]"#,
+            r#"x" {"key": "value"} y" ["a", "b"] "[1]"#,
+        ];
+        let configs: [fn(&mut Carver<&[u8]>); 15] = [
+            |_| {},
+            |carver| carver.max_size(Some(7)),
+            |carver| carver.salvage(true),
+            |carver| carver.recover_suffixes(Some(12)),
+            |carver| {
+                carver.max_size(Some(7));
+                carver.salvage(true);
+                carver.recover_suffixes(Some(64));
+            },
+            |carver| carver.rank_repairs(Some(5)),
+            |carver| {
+                carver.max_size(Some(7));
+                carver.salvage(true);
+                carver.recover_suffixes(Some(64));
+                carver.rank_repairs(Some(64));
+            },
+            |carver| {
+                carver.salvage(true);
+                carver.recover_suffixes(Some(12));
+                carver.min_score = Some(0);
+            },
+            |carver| {
+                carver.max_size(Some(7));
+                carver.salvage(true);
+                carver.recover_suffixes(Some(64));
+                carver.rank_repairs(Some(5));
+                carver.min_score = Some(20);
+            },
+            |carver| carver.exhaustive(true),
+            |carver| {
+                carver.max_size(Some(7));
+                carver.salvage(true);
+                carver.recover_suffixes(Some(12));
+                carver.rank_repairs(Some(5));
+                carver.min_score = Some(0);
+                carver.exhaustive(true);
+            },
+            |carver| carver.strings(Some(0)),
+            |carver| {
+                carver.max_size(Some(7));
+                carver.salvage(true);
+                carver.recover_suffixes(Some(12));
+                carver.rank_repairs(Some(5));
+                carver.strings(Some(2));
+            },
+            |carver| carver.strict(true),
+            |carver| {
+                carver.max_size(Some(7));
+                carver.salvage(true);
+                carver.recover_suffixes(Some(12));
+                carver.rank_repairs(Some(5));
+                carver.exhaustive(true);
+                carver.strings(Some(2));
+                carver.strict(true);
+            },
+        ];
+        for (buf, setup) in bufs.iter().flat_map(|buf| configs.map(|c| (buf, c))) {
+            let mut carver = Carver::new(buf.as_bytes(), None, None);
+            carver.min_size = 0;
+            carver.fix_incomplete = true;
+            setup(&mut carver);
+            let mut expected = Hits::default();
+            carver.parse(&mut expected).unwrap();
+
+            for chunk_size in 1..=buf.len() {
+                for threads in 1..=3 {
+                    let mut hits = Hits::default();
+                    carver
+                        .parse_chunks(buf.as_bytes(), threads, chunk_size, &mut hits)
+                        .unwrap();
+                    assert_eq!(hits.0, expected.0, "chunk size {chunk_size}");
+                }
+            }
+        }
+    }
+}
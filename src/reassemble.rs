@@ -0,0 +1,300 @@
+//! Reassemble JSON strings that were split across non-contiguous regions.
+//!
+//! In memory images, a JSON string is often split across pages or heap
+//! chunks that are not adjacent. The carver then finds its beginning as a
+//! corrupted or exhausted JSON string, and its end as a recovered suffix,
+//! possibly far away from each other. The beginning leaves some brackets open,
+//! and the end closes some brackets that it never opened. If the two match,
+//! and the beginning stops where the end can pick up, i.e., right before an
+//! array element, an object member, or the value of an object member, they are
+//! stitched together into a single JSON string.
+
+use std::collections::HashMap;
+use std::io;
+use std::io::Write;
+
+use crate::sink::write_json_string;
+use crate::{
+    CHAR_COLON, CHAR_COMMA, CHAR_LEFT_CURLY_BRACKET, CHAR_LEFT_SQUARE_BRACKET, Carver, Hit,
+    JsonTracker, Sink, State, is_whitespace,
+};
+
+/// A part of a JSON string that was found in the byte stream.
+#[derive(Debug)]
+struct Fragment {
+    /// The position of the first byte of the fragment in the byte stream.
+    start: usize,
+    /// The position of the last byte of the fragment in the byte stream.
+    end: usize,
+    /// The brackets that are open where the two fragments meet, outermost
+    /// first.
+    stack: Vec<u8>,
+    /// Whether the fragments meet at the value of an object member, i.e.,
+    /// whether the beginning ends with a key and a colon, and the end starts
+    /// with a value whose key is missing.
+    at_value: bool,
+    /// Whether the beginning ends right after an opening bracket, so that the
+    /// end does not need a comma in front of it.
+    open: bool,
+    bytes: Vec<u8>,
+}
+
+/// A JSON string that was stitched together from fragments.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Reassembled {
+    /// The (start, end) positions of the parts of the byte stream that the
+    /// JSON string was stitched together from, last byte included, in the
+    /// order that they appear in it.
+    pub ranges: Vec<(usize, usize)>,
+    /// The reassembled JSON string.
+    pub data: Vec<u8>,
+}
+
+/// A sink that collects the fragments of JSON strings, so that they can be
+/// reassembled once the byte stream has been carved.
+///
+/// The beginnings are collected from the corrupted and exhausted JSON
+/// strings, and the ends from the recovered suffixes (see
+/// [`crate::Carver::recover_suffixes`]). The fragments are kept in memory
+/// until the reassembly.
+#[derive(Debug)]
+pub struct Reassembler {
+    // A parser with the settings of the carver, for scanning the beginnings
+    // again.
+    rescanner: JsonTracker,
+    prefixes: Vec<Fragment>,
+    suffixes: Vec<Fragment>,
+}
+
+impl Reassembler {
+    /// Create a sink that collects the fragments of the JSON strings that
+    /// `carver` finds, and scans them with the same settings.
+    pub fn new<R>(carver: &Carver<R>) -> Self {
+        Reassembler {
+            rescanner: carver.rescanner(),
+            prefixes: vec![],
+            suffixes: vec![],
+        }
+    }
+
+    /// Keep the beginning of a JSON string that did not complete, up to the
+    /// point where it can be continued.
+    fn add_prefix(&mut self, hit: &Hit) -> io::Result<()> {
+        let data = match &hit.spooled {
+            None => hit.data.clone(),
+            Some(_) => hit.clone().into_owned()?.data,
+        };
+        let jt = match self.rescanner.rescan(&data) {
+            (jt, None) => jt,
+            (_, Some(_)) => return Ok(()),
+        };
+        let (len, depth, at_value) = match jt.state {
+            State::Colon => (data.len(), jt.ident_levels.len(), true),
+            _ => (jt.value_end, jt.value_depth, false),
+        };
+        if depth == 0 {
+            return Ok(());
+        }
+        let open = data[..len]
+            .iter()
+            .rfind(|&&b| !is_whitespace(b))
+            .is_some_and(|&b| matches!(b, CHAR_LEFT_SQUARE_BRACKET | CHAR_LEFT_CURLY_BRACKET));
+        self.prefixes.push(Fragment {
+            start: hit.start,
+            end: hit.start + len - 1,
+            stack: jt.ident_levels[..depth].to_vec(),
+            at_value,
+            open,
+            bytes: data[..len].to_vec(),
+        });
+        Ok(())
+    }
+
+    /// Keep the end of a JSON string whose beginning was overwritten.
+    fn add_suffix(&mut self, hit: &Hit) {
+        let Some(repaired) = &hit.repaired else {
+            return;
+        };
+        // The brackets were re-opened in front of the recovered suffix.
+        let reopened = &repaired[..repaired.len() - hit.data.len()];
+        let stack: Vec<u8> = reopened
+            .iter()
+            .copied()
+            .filter(|&b| matches!(b, CHAR_LEFT_SQUARE_BRACKET | CHAR_LEFT_CURLY_BRACKET))
+            .collect();
+        if stack.is_empty() {
+            return;
+        }
+        self.suffixes.push(Fragment {
+            start: hit.start,
+            end: hit.end,
+            stack,
+            at_value: reopened.ends_with(&[CHAR_COLON]),
+            open: false,
+            bytes: hit.data.to_vec(),
+        });
+    }
+
+    /// Stitch the beginnings of JSON strings to compatible ends, and return
+    /// the reassembled JSON strings, in the order of their beginnings.
+    ///
+    /// Every fragment is used at most once. The closest pairs of fragments
+    /// are stitched first, and pairs where the end follows the beginning are
+    /// preferred over pairs where it precedes it.
+    pub fn reassemble(&self) -> Vec<Reassembled> {
+        // Only the ends that close the brackets that a beginning leaves open,
+        // at the same point, are compatible with it.
+        let mut buckets: HashMap<(&[u8], bool), Vec<usize>> = HashMap::new();
+        for (j, suffix) in self.suffixes.iter().enumerate() {
+            buckets
+                .entry((&suffix.stack, suffix.at_value))
+                .or_default()
+                .push(j);
+        }
+
+        let mut pairs = vec![];
+        for (i, prefix) in self.prefixes.iter().enumerate() {
+            let Some(bucket) = buckets.get(&(&prefix.stack[..], prefix.at_value)) else {
+                continue;
+            };
+            for &j in bucket {
+                let suffix = &self.suffixes[j];
+                if suffix.start > prefix.end || suffix.end < prefix.start {
+                    let distance = suffix.start.abs_diff(prefix.end);
+                    pairs.push(((suffix.start < prefix.start, distance), i, j));
+                }
+            }
+        }
+        pairs.sort_unstable();
+
+        let mut matches = vec![None; self.prefixes.len()];
+        let mut used = vec![false; self.suffixes.len()];
+        for (_, i, j) in pairs {
+            if matches[i].is_none() && !used[j] {
+                matches[i] = Some(j);
+                used[j] = true;
+            }
+        }
+
+        let mut reassembled = vec![];
+        for (prefix, j) in self.prefixes.iter().zip(matches) {
+            let Some(suffix) = j.map(|j| &self.suffixes[j]) else {
+                continue;
+            };
+            let mut data = prefix.bytes.clone();
+            if !prefix.at_value && !prefix.open {
+                data.push(CHAR_COMMA);
+            }
+            data.extend_from_slice(&suffix.bytes);
+            reassembled.push(Reassembled {
+                ranges: vec![(prefix.start, prefix.end), (suffix.start, suffix.end)],
+                data,
+            });
+        }
+        reassembled
+    }
+
+    /// Reassemble the JSON strings, and write them to `writer`, one per line.
+    ///
+    /// Every line is a JSON object with the following fields:
+    ///
+    /// ```text
+    /// {"ranges":[[0,10],[16,21]],"reassembled":"{\"a\": [1, 2,3, 4]}"}
+    /// ```
+    ///
+    /// where `ranges` holds the (start, end) positions of the fragments that
+    /// the JSON string was stitched together from, last byte included, and
+    /// `reassembled` is the JSON string, escaped within a JSON string.
+    pub fn write_log<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        for reassembled in self.reassemble() {
+            writer.write_all(b"{\"ranges\":[")?;
+            for (i, (start, end)) in reassembled.ranges.iter().enumerate() {
+                if i > 0 {
+                    writer.write_all(b",")?;
+                }
+                write!(writer, "[{start},{end}]")?;
+            }
+            writer.write_all(b"],\"reassembled\":")?;
+            write_json_string(&mut writer, &reassembled.data)?;
+            writer.write_all(b"}\n")?;
+        }
+        writer.flush()
+    }
+}
+
+impl Sink for Reassembler {
+    fn on_corrupted(&mut self, hit: &Hit) -> io::Result<()> {
+        self.add_prefix(hit)
+    }
+
+    fn on_exhausted(&mut self, hit: &Hit) -> io::Result<()> {
+        self.add_prefix(hit)
+    }
+
+    fn on_suffix_recovered(&mut self, hit: &Hit) -> io::Result<()> {
+        self.add_suffix(hit);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use crate::DEFAULT_SUFFIX_WINDOW;
+    use crate::tests::create_carver;
+
+    use super::*;
+
+    // The (start, end) positions of a fragment, last byte included.
+    type Range = (usize, usize);
+
+    fn reassemble(buf: &[u8]) -> Vec<Reassembled> {
+        let mut carver = create_carver(buf);
+        carver.recover_suffixes(Some(DEFAULT_SUFFIX_WINDOW));
+        let mut reassembler = Reassembler::new(&carver);
+        carver.parse(&mut reassembler).unwrap();
+        reassembler.reassemble()
+    }
+
+    #[rstest]
+    #[case(
+        b"{\"a\": [1, 2, \x00\x00 3, 4]}",
+        vec![((0, 10), (16, 21), r#"{"a": [1, 2,3, 4]}"#)]
+    )]
+    #[case(
+        b"\"v\"}, \"c\": 2}\x00\x00{\"a\": {\"b\": ",
+        vec![((15, 26), (0, 12), r#"{"a": {"b": "v"}, "c": 2}"#)]
+    )]
+    #[case(b"[[\x00\x001, 2]]", vec![((0, 1), (4, 9), "[[1, 2]]")])]
+    #[case(b"[1, 2\x00\"a\": 1}", vec![])]
+    #[case(b"[1, 2\x00\x00\x003]]", vec![])]
+    #[case(
+        b"[{\"a\": 1}, \x00[\"b\", \x00\"c\"] \"d\"]",
+        vec![((0, 8), (24, 27), r#"[{"a": 1},"d"]"#), ((12, 15), (19, 22), r#"["b","c"]"#)]
+    )]
+    fn test_reassemble(#[case] buf: &[u8], #[case] expected: Vec<(Range, Range, &str)>) {
+        let expected: Vec<Reassembled> = expected
+            .into_iter()
+            .map(|(prefix, suffix, data)| Reassembled {
+                ranges: vec![prefix, suffix],
+                data: data.as_bytes().to_vec(),
+            })
+            .collect();
+        assert_eq!(reassemble(buf), expected);
+    }
+
+    #[test]
+    fn test_write_log() {
+        let mut carver = create_carver(b"[1,\n2,\x00\n3]");
+        carver.recover_suffixes(Some(DEFAULT_SUFFIX_WINDOW));
+        let mut reassembler = Reassembler::new(&carver);
+        carver.parse(&mut reassembler).unwrap();
+        let mut log = vec![];
+        reassembler.write_log(&mut log).unwrap();
+        assert_eq!(
+            String::from_utf8(log).unwrap(),
+            "{\"ranges\":[[0,4],[8,9]],\"reassembled\":\"[1,\\n2,3]\"}\n"
+        );
+    }
+}
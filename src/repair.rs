@@ -0,0 +1,339 @@
+//! Rank the ways in which an incomplete JSON string can be repaired.
+//!
+//! There is usually more than one way to repair a JSON string. Truncating it
+//! right after its last complete value is always possible, but it may drop
+//! most of its content. If the carver has looked ahead past the byte that
+//! corrupted it, dropping a few bytes and continuing from there may keep much
+//! more. Every candidate repair records the edits that it makes to the byte
+//! stream, so that it can be reproduced and justified.
+
+use std::cmp::Reverse;
+use std::ops::Range;
+
+use crate::{
+    _closing_ident, CHAR_COMMA, CHAR_QUOT_MARK, JsonTracker, RepairMode, State, is_whitespace,
+    replace_newlines,
+};
+
+/// The default number of bytes that the carver looks ahead at, past the byte
+/// that corrupted a JSON string, when it ranks repair candidates.
+pub const DEFAULT_REPAIR_LOOKAHEAD: usize = 4 << 10; // 4KiB
+
+// The maximum number of corrupted bytes that a repair candidate can skip.
+const MAX_SKIPPED_RUN: usize = 16;
+
+/// The way in which a repair candidate fixes an incomplete JSON string.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Strategy {
+    /// Truncate the JSON string right after its last complete value, and
+    /// close the brackets that are still open.
+    Truncate,
+    /// Close the string value that the JSON string stopped in, and the
+    /// brackets that are still open.
+    CloseString,
+    /// Drop the comma right before a closing bracket, and continue the JSON
+    /// string from that bracket.
+    DropTrailingComma,
+    /// Skip a run of corrupted bytes, and continue the JSON string from the
+    /// first byte that can follow.
+    SkipCorrupted,
+}
+
+impl Strategy {
+    /// The name of the strategy, as it appears in repair logs.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Strategy::Truncate => "truncate",
+            Strategy::CloseString => "close_string",
+            Strategy::DropTrailingComma => "drop_trailing_comma",
+            Strategy::SkipCorrupted => "skip_corrupted",
+        }
+    }
+}
+
+/// An edit that a repair candidate makes to the byte stream.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Edit {
+    /// The bytes from `start` to `end` were dropped, last byte included.
+    Drop {
+        /// The position of the first dropped byte in the byte stream.
+        start: usize,
+        /// The position of the last dropped byte in the byte stream.
+        end: usize,
+    },
+    /// The `bytes` were inserted right before the byte at position `at`.
+    Insert {
+        /// The position in the byte stream that the bytes were inserted at.
+        at: usize,
+        /// The inserted bytes.
+        bytes: Vec<u8>,
+    },
+}
+
+/// A way to repair an incomplete JSON string (see
+/// [`crate::Carver::rank_repairs`]).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Candidate {
+    /// How the candidate repairs the JSON string.
+    pub strategy: Strategy,
+    /// The position of the last byte that the candidate covers in the byte
+    /// stream. It lies past the end of the JSON string, if the candidate
+    /// continues past the byte that corrupted it.
+    pub end: usize,
+    /// The number of bytes of the byte stream that the candidate keeps.
+    /// Candidates are ranked by it.
+    pub retained: usize,
+    /// The edits that turn the bytes from the start of the JSON string up to
+    /// `end` into the repaired version, in the order of their positions.
+    pub edits: Vec<Edit>,
+    /// The repaired version of the JSON string.
+    pub repaired: Vec<u8>,
+}
+
+impl JsonTracker {
+    /// Return the candidate repairs for the current JSON string, best first.
+    ///
+    /// `data` holds every byte of the JSON string, followed by the bytes that
+    /// the carver has looked ahead at, past the byte that corrupted it.
+    pub(crate) fn candidates(&self, data: &[u8], replace: bool) -> Vec<Candidate> {
+        let mut candidates = vec![];
+        let closers = self.closers(self.value_depth);
+        candidates.push(self.candidate(
+            Strategy::Truncate,
+            0..0,
+            self.value_end,
+            closers,
+            self.cur,
+            data,
+        ));
+        if let Some(end) = self.open_string_end(data) {
+            let mut insert = vec![CHAR_QUOT_MARK];
+            insert.extend(self.closers(self.ident_levels.len()));
+            candidates.push(self.candidate(
+                Strategy::CloseString,
+                0..0,
+                end,
+                insert,
+                self.cur,
+                data,
+            ));
+        }
+
+        if let State::ArrayComma | State::ObjectComma = self.state {
+            let comma = data[..self.cur].iter().rposition(|&b| !is_whitespace(b));
+            if let Some(comma) = comma.filter(|&comma| data[comma] == CHAR_COMMA) {
+                candidates.extend(self.resume(
+                    Strategy::DropTrailingComma,
+                    comma..comma + 1,
+                    self.cur,
+                    State::ValueEnd,
+                    data,
+                ));
+            }
+        }
+
+        // Keep the run that retains the most bytes, or the shortest one, if
+        // there is a tie.
+        let best = (1..=MAX_SKIPPED_RUN)
+            .take_while(|run| self.cur + run < data.len())
+            .filter_map(|run| {
+                let skipped = self.cur..self.cur + run;
+                self.resume(
+                    Strategy::SkipCorrupted,
+                    skipped,
+                    self.cur + run,
+                    self.state,
+                    data,
+                )
+            })
+            .reduce(|best, candidate| match candidate.retained > best.retained {
+                true => candidate,
+                false => best,
+            });
+        candidates.extend(best);
+
+        // Prefer the simplest strategy, if two candidates retain as many
+        // bytes.
+        candidates.sort_by_key(|candidate| Reverse(candidate.retained));
+        let mut ranked: Vec<Candidate> = Vec::with_capacity(candidates.len());
+        for mut candidate in candidates {
+            if ranked.iter().all(|c| c.repaired != candidate.repaired) {
+                if replace {
+                    replace_newlines(&mut candidate.repaired);
+                }
+                ranked.push(candidate);
+            }
+        }
+        ranked
+    }
+
+    /// Return the bytes that close the `depth` outermost open brackets.
+    fn closers(&self, depth: usize) -> Vec<u8> {
+        self.ident_levels[..depth]
+            .iter()
+            .rev()
+            .map(|&ident| _closing_ident(ident))
+            .collect()
+    }
+
+    /// Create a candidate that drops the `dropped` bytes of `data`, keeps the
+    /// rest of them up to `end`, and inserts `insert` at `stop`, where the
+    /// candidate stops covering the byte stream. The positions are relative
+    /// to the start of the JSON string.
+    fn candidate(
+        &self,
+        strategy: Strategy,
+        dropped: Range<usize>,
+        end: usize,
+        insert: Vec<u8>,
+        stop: usize,
+        data: &[u8],
+    ) -> Candidate {
+        let mut edits = vec![];
+        let mut repaired = Vec::with_capacity(end + insert.len());
+        let mut retained = 0;
+        let mut pos = 0;
+        for range in [0..dropped.start, dropped.end..end, stop..stop] {
+            if range.start > pos {
+                edits.push(Edit::Drop {
+                    start: self.start + pos,
+                    end: self.start + range.start - 1,
+                });
+            }
+            repaired.extend_from_slice(&data[range.clone()]);
+            retained += range.len();
+            pos = range.end;
+        }
+        if !insert.is_empty() {
+            repaired.extend_from_slice(&insert);
+            edits.push(Edit::Insert {
+                at: self.start + stop,
+                bytes: insert,
+            });
+        }
+        Candidate {
+            strategy,
+            end: self.start + stop - 1,
+            retained,
+            edits,
+            repaired,
+        }
+    }
+
+    /// Drop the `dropped` bytes, and continue the JSON string from `state`,
+    /// with the bytes of `data` from `from` onwards. Return a candidate, if
+    /// it keeps some of these bytes.
+    fn resume(
+        &self,
+        strategy: Strategy,
+        dropped: Range<usize>,
+        from: usize,
+        state: State,
+        data: &[u8],
+    ) -> Option<Candidate> {
+        // The positions of the trial tracker are relative to the start of
+        // the JSON string, so that they point into `data`.
+        let mut trial = JsonTracker::new(Some(0), Some(self.max_ident_depth));
+        trial.cur = from;
+        trial.partial_close_end = self.partial_close_end;
+        trial.ident_levels = self.ident_levels.clone();
+        trial.ident_starts = self.ident_starts.clone();
+        trial.ident_features = self.ident_features.clone();
+        trial.value_end = self.value_end;
+        trial.value_depth = self.value_depth;
+        trial.string_start = self.string_start;
+        trial.in_key = self.in_key;
+        trial.state = state;
+
+        let (_, cause) = trial.feed(&data[from..]);
+        let (end, depth, quote) = match trial.ident_levels.is_empty() {
+            true => (trial.cur, 0, None),
+            false => trial.repair_point(data, RepairMode::Values),
+        };
+        if end <= from {
+            return None;
+        }
+        // The trial may stop at the end of the bytes without a cause.
+        let stop = match cause {
+            None => data.len(),
+            Some(_) => trial.cur,
+        };
+        let mut insert: Vec<u8> = quote.into_iter().collect();
+        insert.extend(trial.closers(depth));
+        Some(self.candidate(strategy, dropped, end, insert, stop, data))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use crate::tests::carve_with;
+
+    use super::*;
+
+    fn rank(buf: &[u8]) -> Vec<Candidate> {
+        let hits = carve_with(buf, |carver| {
+            carver.rank_repairs(Some(DEFAULT_REPAIR_LOOKAHEAD))
+        });
+        hits.into_iter().next().unwrap().candidates
+    }
+
+    #[rstest]
+    #[case(
+        br#"{"a": 1, "b": 2,}"#,
+        br#"{"a": 1, "b": 2}"#,
+        Strategy::DropTrailingComma
+    )]
+    #[case(b"[1, \x00\x00 2, 3]", b"[1,  2, 3]", Strategy::SkipCorrupted)]
+    #[case(b"[\"abc\x01def\", 4]", br#"["abcdef", 4]"#, Strategy::SkipCorrupted)]
+    #[case(br#"{"msg": "cut"#, br#"{"msg": "cut"}"#, Strategy::CloseString)]
+    #[case(br#"[1, {"a": tru"#, br#"[1, {}]"#, Strategy::Truncate)]
+    fn test_rank(#[case] buf: &[u8], #[case] best: &[u8], #[case] strategy: Strategy) {
+        let candidates = rank(buf);
+        assert_eq!(candidates[0].repaired, best);
+        assert_eq!(candidates[0].strategy, strategy);
+        for pair in candidates.windows(2) {
+            assert!(pair[0].retained >= pair[1].retained);
+        }
+    }
+
+    #[test]
+    fn test_edits() {
+        let candidates = rank(b"xx[1, \x00\x002, [3");
+        assert_eq!(
+            candidates,
+            [
+                Candidate {
+                    strategy: Strategy::SkipCorrupted,
+                    end: 12,
+                    retained: 8,
+                    edits: vec![
+                        Edit::Drop { start: 6, end: 7 },
+                        // The number may continue past the end of the stream.
+                        Edit::Drop { start: 12, end: 12 },
+                        Edit::Insert {
+                            at: 13,
+                            bytes: b"]]".to_vec()
+                        },
+                    ],
+                    repaired: b"[1, 2, []]".to_vec(),
+                },
+                Candidate {
+                    strategy: Strategy::Truncate,
+                    end: 5,
+                    retained: 2,
+                    edits: vec![
+                        Edit::Drop { start: 4, end: 5 },
+                        Edit::Insert {
+                            at: 6,
+                            bytes: b"]".to_vec()
+                        },
+                    ],
+                    repaired: b"[1]".to_vec(),
+                },
+            ]
+        );
+    }
+}
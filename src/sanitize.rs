@@ -0,0 +1,269 @@
+//! Replace the invalid Unicode within the strings of the JSON strings that
+//! are written.
+//!
+//! The carver accepts any byte above 0x7F within strings, and any `\uXXXX`
+//! escape sequence, so that it can keep the JSON strings of other encodings
+//! and of sloppy writers. Their structure is valid, but tools that expect
+//! valid Unicode may reject them. Sanitizing them keeps their structure, and
+//! replaces the invalid UTF-8 sequences and the unpaired surrogate escapes
+//! with escape sequences that are valid.
+
+use std::io;
+use std::io::Write;
+
+use crate::{CHAR_ESCAPE, CHAR_U, Hit, UTF8_CONTINUATION, literal, utf8_lead};
+
+/// How to replace the invalid Unicode within strings (see
+/// [`crate::JsonLines::sanitize`]).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Sanitize {
+    /// Replace every invalid UTF-8 sequence, as long as it could have been
+    /// the start of a character, with `\ufffd`, the replacement character.
+    Replacement,
+    /// Replace every byte of an invalid UTF-8 sequence with the Latin-1
+    /// character that it stands for, e.g., `\u00e9`.
+    Latin1,
+}
+
+/// The escape sequence of U+FFFD, which stands for unpaired surrogates, and
+/// for invalid UTF-8 sequences if they are not reinterpreted as Latin-1.
+const REPLACEMENT: &[u8] = br"\ufffd";
+
+/// A part of a string that may have to be replaced.
+enum Token {
+    /// Valid UTF-8 or escape sequence, of this many bytes.
+    Valid(usize),
+    /// Invalid UTF-8 sequence, or unpaired surrogate escape, of this many
+    /// bytes.
+    Invalid(usize),
+    /// Incomplete sequence, which continues in the bytes that follow.
+    Incomplete,
+}
+
+/// Return the token that starts with a backslash or a non-ASCII byte. If
+/// `last` is set, no bytes follow the ones in `buf`.
+fn next_token(buf: &[u8], last: bool) -> Token {
+    let incomplete = |len| match last {
+        true => Token::Invalid(len),
+        false => Token::Incomplete,
+    };
+    if buf[0] == CHAR_ESCAPE {
+        // The carver has already checked the escape sequences of the JSON
+        // strings that are written.
+        return match buf.get(1) {
+            None if !last => Token::Incomplete,
+            Some(&CHAR_U) => match (buf.len() >= 6, literal::hex4(&buf[2..])) {
+                (false, _) if !last => Token::Incomplete,
+                (_, Some(0xD800..=0xDBFF)) => {
+                    let low = match buf.get(6..8) {
+                        Some(b"\\u") => literal::hex4(&buf[8..]),
+                        _ => None,
+                    };
+                    match low {
+                        Some(0xDC00..=0xDFFF) => Token::Valid(12),
+                        None if buf.len() < 12 && !last => Token::Incomplete,
+                        _ => Token::Invalid(6),
+                    }
+                }
+                (_, Some(0xDC00..=0xDFFF)) => Token::Invalid(6),
+                _ => Token::Valid(buf.len().min(6)),
+            },
+            _ => Token::Valid(buf.len().min(2)),
+        };
+    }
+    let Some((needed, mut range)) = utf8_lead(buf[0]) else {
+        return Token::Invalid(1);
+    };
+    for len in 1..=needed as usize {
+        match buf.get(len) {
+            None => return incomplete(len),
+            Some(b) if (range.0..=range.1).contains(b) => range = UTF8_CONTINUATION,
+            Some(_) => return Token::Invalid(len),
+        }
+    }
+    Token::Valid(needed as usize + 1)
+}
+
+/// A writer that sanitizes the bytes of a JSON string, as they are written to
+/// another writer.
+pub(crate) struct Sanitizer<W> {
+    writer: W,
+    mode: Sanitize,
+    // The sequence that the last bytes that were written started, if it has
+    // not been completed yet.
+    pending: Vec<u8>,
+    replacements: usize,
+}
+
+impl<W: Write> Sanitizer<W> {
+    pub(crate) fn new(writer: W, mode: Sanitize) -> Self {
+        Sanitizer {
+            writer,
+            mode,
+            pending: vec![],
+            replacements: 0,
+        }
+    }
+
+    /// Sanitize the pending sequence, and return the number of replacements
+    /// that were made.
+    pub(crate) fn finish(mut self) -> io::Result<usize> {
+        let pending = std::mem::take(&mut self.pending);
+        self.sanitize(&pending, true)?;
+        Ok(self.replacements)
+    }
+
+    /// Write the bytes of `buf` sanitized, except for the sequence that they
+    /// end with, if it's incomplete and `last` is not set.
+    fn sanitize(&mut self, buf: &[u8], last: bool) -> io::Result<()> {
+        let mut written = 0;
+        let mut i = 0;
+        while i < buf.len() {
+            if buf[i] != CHAR_ESCAPE && buf[i].is_ascii() {
+                i += 1;
+                continue;
+            }
+            let len = match next_token(&buf[i..], last) {
+                Token::Valid(len) => len,
+                Token::Invalid(len) => {
+                    self.writer.write_all(&buf[written..i])?;
+                    match (self.mode, buf[i]) {
+                        (Sanitize::Latin1, b) if b != CHAR_ESCAPE => {
+                            for b in &buf[i..i + len] {
+                                write!(self.writer, "\\u00{b:02x}")?;
+                            }
+                            self.replacements += len;
+                        }
+                        _ => {
+                            self.writer.write_all(REPLACEMENT)?;
+                            self.replacements += 1;
+                        }
+                    }
+                    written = i + len;
+                    len
+                }
+                Token::Incomplete => {
+                    self.pending.extend_from_slice(&buf[i..]);
+                    break;
+                }
+            };
+            i += len;
+        }
+        self.writer.write_all(&buf[written..i])
+    }
+}
+
+impl<W: Write> Write for Sanitizer<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self.pending.is_empty() {
+            true => self.sanitize(buf, false)?,
+            false => {
+                let mut pending = std::mem::take(&mut self.pending);
+                pending.extend_from_slice(buf);
+                self.sanitize(&pending, false)?;
+            }
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+/// Write `buf` sanitized, after the first `spooled` bytes of the hit, if they
+/// have been spooled, and return the number of replacements that were made.
+pub(crate) fn write_sanitized<W: Write>(
+    writer: W,
+    mode: Sanitize,
+    hit: &Hit,
+    spooled: usize,
+    buf: &[u8],
+) -> io::Result<usize> {
+    let mut sanitizer = Sanitizer::new(writer, mode);
+    if let Some(spool) = &hit.spooled {
+        spool.copy_to(spooled, &mut sanitizer)?;
+    }
+    sanitizer.write_all(buf)?;
+    sanitizer.finish()
+}
+
+/// Return the number of replacements that sanitizing the JSON string that is
+/// written for a hit makes, i.e., its repaired version, if any.
+pub(crate) fn replacements(hit: &Hit, mode: Sanitize) -> io::Result<usize> {
+    let (spooled, buf) = match &hit.repaired {
+        Some(repaired) => (
+            hit.spooled.as_ref().map_or(0, |s| s.repaired_len()),
+            &repaired[..],
+        ),
+        None => (hit.spooled.as_ref().map_or(0, |s| s.len()), &hit.data[..]),
+    };
+    write_sanitized(io::sink(), mode, hit, spooled, buf)
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use super::*;
+
+    fn sanitize(buf: &[u8], mode: Sanitize, chunk_size: usize) -> (String, usize) {
+        let mut out = vec![];
+        let mut sanitizer = Sanitizer::new(&mut out, mode);
+        for chunk in buf.chunks(chunk_size) {
+            sanitizer.write_all(chunk).unwrap();
+        }
+        let replacements = sanitizer.finish().unwrap();
+        (String::from_utf8(out).unwrap(), replacements)
+    }
+
+    #[rstest]
+    #[case(b"[\"caf\xc3\xa9\"]", r#"["café"]"#, r#"["café"]"#, 0)]
+    #[case(b"[\"caf\xe9\"]", r#"["caf\ufffd"]"#, r#"["caf\u00e9"]"#, 1)]
+    #[case(b"[\"\xff\xfe\"]", r#"["\ufffd\ufffd"]"#, r#"["\u00ff\u00fe"]"#, 2)]
+    #[case(b"[\"\xf0\x9f\x98\"]", r#"["\ufffd"]"#, r#"["\u00f0\u009f\u0098"]"#, 1)]
+    #[case(
+        b"[\"\xed\xa0\x80\"]",
+        r#"["\ufffd\ufffd\ufffd"]"#,
+        r#"["\u00ed\u00a0\u0080"]"#,
+        3
+    )]
+    #[case(b"[\"\xc0\xaf\"]", r#"["\ufffd\ufffd"]"#, r#"["\u00c0\u00af"]"#, 2)]
+    #[case(r#"["😀", "é"]"#.as_bytes(), r#"["😀", "é"]"#, r#"["😀", "é"]"#, 0)]
+    #[case(
+        br#"["\ud83d\ude00"]"#,
+        r#"["\ud83d\ude00"]"#,
+        r#"["\ud83d\ude00"]"#,
+        0
+    )]
+    #[case(
+        br#"["\ud83d!", "\ude00\ud83d"]"#,
+        r#"["\ufffd!", "\ufffd\ufffd"]"#,
+        r#"["\ufffd!", "\ufffd\ufffd"]"#,
+        3
+    )]
+    #[case(
+        br#"["\ud83d\n", "\\ud83d"]"#,
+        r#"["\ufffd\n", "\\ud83d"]"#,
+        r#"["\ufffd\n", "\\ud83d"]"#,
+        1
+    )]
+    #[case(br#"["\ud83d"]"#, r#"["\ufffd"]"#, r#"["\ufffd"]"#, 1)]
+    #[case(b"[\"\xc3", "[\"\\ufffd", "[\"\\u00c3", 1)]
+    fn test_sanitize(
+        #[case] buf: &[u8],
+        #[case] replacement: &str,
+        #[case] latin1: &str,
+        #[case] replacements: usize,
+    ) {
+        // The sequences may be split across writes.
+        for chunk_size in 1..=buf.len() {
+            let (out, count) = sanitize(buf, Sanitize::Replacement, chunk_size);
+            assert_eq!(out, replacement);
+            assert_eq!(count, replacements);
+            let (out, _) = sanitize(buf, Sanitize::Latin1, chunk_size);
+            assert_eq!(out, latin1);
+        }
+    }
+}
@@ -0,0 +1,173 @@
+//! Score how likely it is that a carved JSON string is not a false positive.
+//!
+//! Binary data is full of short byte sequences that happen to be valid JSON,
+//! e.g., `[1]`, `[0,0]` or `{}`. Real JSON strings tend to be larger, have
+//! more values, nest deeper, have keys that look like names, and hold text
+//! instead of just numbers. They also tend to be surrounded by other text,
+//! or by zeroed memory, instead of random-looking bytes. The score combines
+//! these features into a number from 0 to 100.
+
+/// The number of bytes right before a JSON string whose entropy affects its
+/// score.
+const SCORE_CONTEXT: usize = 64;
+
+/// The number of bytes that the carver has to look back at, so that it can
+/// score the JSON strings that it has just finished.
+///
+/// JSON strings that started further back than that are scored without the
+/// bytes before them.
+pub(crate) const SCORE_WINDOW: usize = 4 << 10; // 4KiB
+
+// The maximum length of a key that looks like a name.
+const MAX_KEY_LEN: usize = 64;
+
+/// The features of a JSON string that the state machine keeps track of. They
+/// are used for scoring and filtering JSON strings.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) struct Features {
+    /// Whether the JSON string is an object, rather than an array.
+    pub(crate) object: bool,
+    /// The maximum nesting depth.
+    pub(crate) depth: usize,
+    /// The number of keys of the object, or of elements of the array.
+    pub(crate) members: usize,
+    /// The number of values, apart from the outermost one and the keys.
+    pub(crate) values: usize,
+    /// The number of keys.
+    pub(crate) keys: usize,
+    /// The number of keys that look like names.
+    pub(crate) plausible_keys: usize,
+    /// The number of string values.
+    pub(crate) strings: usize,
+    /// The number of numbers.
+    pub(crate) numbers: usize,
+}
+
+impl Features {
+    /// Return the counts of the features that were added since `before`. The
+    /// rest of the features have to be set separately.
+    pub(crate) fn since(&self, before: &Features) -> Features {
+        Features {
+            object: false,
+            depth: 0,
+            members: 0,
+            values: self.values - before.values,
+            keys: self.keys - before.keys,
+            plausible_keys: self.plausible_keys - before.plausible_keys,
+            strings: self.strings - before.strings,
+            numbers: self.numbers - before.numbers,
+        }
+    }
+}
+
+/// Whether a byte can be part of a key that looks like a name. Non-ASCII
+/// bytes are allowed, so that names in any language count.
+pub(crate) fn is_key_byte(b: u8) -> bool {
+    b.is_ascii_alphanumeric()
+        || matches!(b, b'_' | b'-' | b'.' | b' ' | b'$' | b'@' | b':')
+        || b >= 0x80
+}
+
+/// Whether a key of `len` bytes, whose bytes all look like part of a name,
+/// looks like a name.
+pub(crate) fn is_plausible_key(len: usize) -> bool {
+    (1..=MAX_KEY_LEN).contains(&len)
+}
+
+/// Return the bytes right before the JSON string that starts at `start`, if
+/// they can be looked at.
+///
+/// `lookback` holds the bytes of the stream right before position `pos`.
+pub(crate) fn context(lookback: &[u8], pos: usize, start: usize) -> Option<&[u8]> {
+    let from = start.saturating_sub(SCORE_CONTEXT);
+    let offset = pos - lookback.len();
+    // The carver always keeps the last window of bytes, so checking against
+    // it, instead of the length of the lookback, makes the score independent
+    // of how the stream is read.
+    match pos - from <= SCORE_WINDOW && from >= offset && from < start {
+        true => Some(&lookback[from - offset..start - offset]),
+        false => None,
+    }
+}
+
+/// Return the Shannon entropy of `buf`, in bits per byte, normalized from 0
+/// to 1.
+fn entropy(buf: &[u8]) -> f64 {
+    let mut counts = [0usize; 256];
+    for &b in buf {
+        counts[b as usize] += 1;
+    }
+    let len = buf.len() as f64;
+    let bits: f64 = counts
+        .iter()
+        .filter(|&&count| count > 0)
+        .map(|&count| {
+            let p = count as f64 / len;
+            -p * p.log2()
+        })
+        .sum();
+    bits / 8.0
+}
+
+/// Return the score of a JSON string of `size` bytes, from 0 to 100, given
+/// its features and the bytes right before it, if any.
+pub(crate) fn score(features: &Features, size: usize, context: Option<&[u8]>) -> u8 {
+    let ratio = |part: usize, whole: usize| match whole {
+        0 => 0.0,
+        _ => (part as f64 / whole as f64).min(1.0),
+    };
+    let mut score = 25.0 * ratio(features.values, 8);
+    score += 15.0 * ratio(features.depth.saturating_sub(1), 3);
+    score += 20.0 * ratio(features.plausible_keys, features.keys);
+    // Text is more likely than tables of numbers.
+    score += 15.0 * ratio(features.strings, features.strings + features.numbers);
+    score += 10.0 * ratio(size, 64);
+    // Random-looking bytes around a JSON string suggest that it's a false
+    // positive. Without them, the score is halfway.
+    score += 15.0
+        * match context {
+            Some(context) => 1.0 - entropy(context),
+            None => 0.5,
+        };
+    score.round() as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use crate::tests::carve_with;
+
+    use super::*;
+
+    fn score_of(buf: &[u8]) -> u8 {
+        let hits = carve_with(buf, |carver| carver.min_score = Some(0));
+        hits[0].score.unwrap()
+    }
+
+    #[rstest]
+    #[case(vec![], "[1]", 11)]
+    // Zeroed memory around a JSON string is a good sign.
+    #[case(vec![0; 64], "[0,0]", 22)]
+    // Random-looking bytes around a JSON string are a bad sign.
+    #[case((0..64).map(|i| i * 4 + 2).collect(), "[0,0]", 11)]
+    #[case(vec![], r#"{"name": "Ann", "age": 30}"#, 45)]
+    #[case(
+        b"msg: ".to_vec(),
+        r#"{"from": "ann", "to": ["bob", "eve"], "body": {"text": "hi", "sent": 17}}"#,
+        80
+    )]
+    fn test_score(#[case] prefix: Vec<u8>, #[case] json: &str, #[case] expected: u8) {
+        let buf = [&prefix[..], json.as_bytes()].concat();
+        assert_eq!(score_of(&buf), expected);
+    }
+
+    #[rstest]
+    #[case(b"aaaa", 0.0)]
+    #[case(b"aabb", 0.125)]
+    #[case(b"abcd", 0.25)]
+    #[case(&[0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15], 0.5)]
+    fn test_entropy(#[case] buf: &[u8], #[case] expected: f64) {
+        assert_eq!(entropy(buf), expected);
+    }
+}
@@ -0,0 +1,543 @@
+//! Destinations for the JSON strings that the carver finds.
+
+use std::io;
+use std::io::Write;
+
+use crate::sanitize::{self, Sanitize};
+use crate::{
+    CHAR_CARRIAGE_RETURN, CHAR_ESC_CARRIAGE_RETURN, CHAR_ESC_LINE_FEED, CHAR_ESC_TAB, CHAR_ESCAPE,
+    CHAR_NEWLINE, CHAR_QUOT_MARK, CHAR_SPACE, CHAR_TAB, CHAR_U, Candidate, Edit, Hit, Status,
+    literal,
+};
+
+/// A destination for carved JSON strings and their reports.
+///
+/// Every callback but `on_malformed()` receives the [`Hit`] that triggered
+/// it, which holds the position of the JSON string in the byte stream and the
+/// carved bytes. All
+/// callbacks do nothing by default, so implementors need to override only the
+/// ones they care about.
+pub trait Sink {
+    /// Called for every structurally valid JSON string.
+    fn on_completed(&mut self, _hit: &Hit) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Called for every JSON string that was interrupted by an unexpected
+    /// byte.
+    fn on_corrupted(&mut self, _hit: &Hit) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Called for the JSON string that was interrupted by the end of the
+    /// byte stream.
+    fn on_exhausted(&mut self, _hit: &Hit) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Called for every JSON string that was nested deeper than the maximum
+    /// identation depth of the carver.
+    fn on_too_deep(&mut self, _hit: &Hit) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Called for every JSON string that grew larger than the maximum size of
+    /// the carver.
+    fn on_too_large(&mut self, _hit: &Hit) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Called for every structurally valid JSON string that was salvaged
+    /// from a JSON string that did not complete (see
+    /// [`crate::Carver::salvage`]).
+    fn on_salvaged(&mut self, _hit: &Hit) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Called for every JSON string whose beginning was overwritten, and
+    /// whose end has been recovered (see [`crate::Carver::recover_suffixes`]).
+    /// The recovered JSON string is passed to `on_repaired()` afterwards.
+    fn on_suffix_recovered(&mut self, _hit: &Hit) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Called for every JSON string that was interrupted by the first byte of
+    /// a page (see [`crate::Carver::page_size`]). By default, it's handled
+    /// like any other corrupted JSON string.
+    fn on_page_boundary(&mut self, hit: &Hit) -> io::Result<()> {
+        self.on_corrupted(hit)
+    }
+
+    /// Called for every string literal outside of any array or object (see
+    /// [`crate::Carver::strings`]).
+    fn on_string(&mut self, _hit: &Hit) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Called for every JSON string that was interrupted by a string that is
+    /// not valid Unicode (see [`crate::Carver::strict`]). By default, it's
+    /// handled like any other corrupted JSON string.
+    fn on_invalid_unicode(&mut self, hit: &Hit) -> io::Result<()> {
+        self.on_corrupted(hit)
+    }
+
+    /// Called after `on_corrupted()`, `on_exhausted()`, `on_too_deep()`,
+    /// `on_too_large()`, `on_suffix_recovered()`, `on_page_boundary()` or
+    /// `on_invalid_unicode()`, if the carver has managed to repair the JSON
+    /// string. If the hit has been spooled,
+    /// `repaired` holds only the bytes that follow the spooled ones (see
+    /// [`crate::Spooled::repaired_len`]).
+    fn on_repaired(&mut self, _hit: &Hit, _repaired: &[u8]) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Called after `on_repaired()`, or in its place, if the carver has
+    /// ranked the ways in which the JSON string can be repaired (see
+    /// [`crate::Carver::rank_repairs`]).
+    fn on_candidates(&mut self, _hit: &Hit, _candidates: &[Candidate]) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Called for every part of the byte stream that could not be parsed, and
+    /// was skipped, e.g., a malformed record of a heap dump (see
+    /// [`crate::Carver::parse_hprof`]), with the position of the error and
+    /// its reason.
+    fn on_malformed(&mut self, _pos: usize, _reason: &str) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Dispatch a hit to the callback that matches its status.
+    fn on_hit(&mut self, hit: &Hit) -> io::Result<()> {
+        match hit.status {
+            Status::Completed => return self.on_completed(hit),
+            Status::Salvaged => return self.on_salvaged(hit),
+            Status::String => return self.on_string(hit),
+            Status::Corrupted => self.on_corrupted(hit)?,
+            Status::Exhausted => self.on_exhausted(hit)?,
+            Status::TooDeep => self.on_too_deep(hit)?,
+            Status::TooLarge => self.on_too_large(hit)?,
+            Status::SuffixRecovered => self.on_suffix_recovered(hit)?,
+            Status::PageBoundary => self.on_page_boundary(hit)?,
+            Status::InvalidUnicode => self.on_invalid_unicode(hit)?,
+        }
+        if let Some(repaired) = &hit.repaired {
+            self.on_repaired(hit, repaired)?;
+        }
+        match hit.candidates.is_empty() {
+            true => Ok(()),
+            false => self.on_candidates(hit, &hit.candidates),
+        }
+    }
+}
+
+/// Implement the forwarding sinks, with every callback of [`Sink`] listed
+/// once, so that none of them falls back to its default.
+macro_rules! forward_sink {
+    ($($callback:ident($($arg:ident: $ty:ty),*);)*) => {
+        impl<S: Sink + ?Sized> Sink for &mut S {
+            $(
+                fn $callback(&mut self, $($arg: $ty),*) -> io::Result<()> {
+                    (**self).$callback($($arg),*)
+                }
+            )*
+        }
+
+        impl<S: Sink + ?Sized> Sink for Box<S> {
+            $(
+                fn $callback(&mut self, $($arg: $ty),*) -> io::Result<()> {
+                    (**self).$callback($($arg),*)
+                }
+            )*
+        }
+
+        /// Forward every hit to both sinks, first to the left one and then to
+        /// the right one.
+        impl<A: Sink, B: Sink> Sink for (A, B) {
+            $(
+                fn $callback(&mut self, $($arg: $ty),*) -> io::Result<()> {
+                    self.0.$callback($($arg),*)?;
+                    self.1.$callback($($arg),*)
+                }
+            )*
+        }
+    };
+}
+
+forward_sink! {
+    on_completed(hit: &Hit);
+    on_corrupted(hit: &Hit);
+    on_exhausted(hit: &Hit);
+    on_too_deep(hit: &Hit);
+    on_too_large(hit: &Hit);
+    on_salvaged(hit: &Hit);
+    on_suffix_recovered(hit: &Hit);
+    on_page_boundary(hit: &Hit);
+    on_string(hit: &Hit);
+    on_invalid_unicode(hit: &Hit);
+    on_repaired(hit: &Hit, repaired: &[u8]);
+    on_candidates(hit: &Hit, candidates: &[Candidate]);
+    on_malformed(pos: usize, reason: &str);
+    on_hit(hit: &Hit);
+}
+
+/// A sink that writes JSON strings to a writer, one per line.
+///
+/// Completed and salvaged strings, as well as string literals, are written as
+/// is, whereas the rest are written only if they have been repaired.
+pub struct JsonLines<W> {
+    writer: W,
+    decode_strings: bool,
+    sanitize: Option<Sanitize>,
+}
+
+impl<W: Write> JsonLines<W> {
+    /// Create a sink that writes JSON strings to `writer`.
+    pub fn new(writer: W) -> Self {
+        JsonLines {
+            writer,
+            decode_strings: false,
+            sanitize: None,
+        }
+    }
+
+    /// Configure whether to write string literals decoded, i.e., without
+    /// their quotes and with their escape sequences replaced, like
+    /// strings(1) would. Control characters are escaped again, so that every
+    /// string literal takes a single line.
+    pub fn decode_strings(&mut self, opt: bool) {
+        self.decode_strings = opt;
+    }
+
+    /// Configure whether to replace the invalid UTF-8 sequences and the
+    /// unpaired surrogate escapes within the strings of the JSON strings,
+    /// and how, so that they are valid Unicode (see [`Sanitize`]).
+    pub fn sanitize(&mut self, mode: Option<Sanitize>) {
+        self.sanitize = mode;
+    }
+
+    /// Return the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    fn write_line(&mut self, buf: &[u8]) -> io::Result<()> {
+        self.writer.write_all(buf)?;
+        self.writer.write_all(&[CHAR_NEWLINE])
+    }
+
+    /// Write the bytes of a hit, after the first `spooled` bytes of it, if
+    /// they have been spooled.
+    fn write_hit(&mut self, hit: &Hit, spooled: usize, buf: &[u8]) -> io::Result<()> {
+        let Some(mode) = self.sanitize else {
+            if let Some(spool) = &hit.spooled {
+                spool.copy_to(spooled, &mut self.writer)?;
+            }
+            return self.write_line(buf);
+        };
+        sanitize::write_sanitized(&mut self.writer, mode, hit, spooled, buf)?;
+        self.writer.write_all(&[CHAR_NEWLINE])
+    }
+}
+
+impl<W: Write> Sink for JsonLines<W> {
+    fn on_completed(&mut self, hit: &Hit) -> io::Result<()> {
+        let spooled = hit.spooled.as_ref().map_or(0, |spooled| spooled.len());
+        self.write_hit(hit, spooled, &hit.data)
+    }
+
+    fn on_salvaged(&mut self, hit: &Hit) -> io::Result<()> {
+        self.on_completed(hit)
+    }
+
+    fn on_string(&mut self, hit: &Hit) -> io::Result<()> {
+        // The carver has already checked that string literals can be
+        // decoded.
+        match (self.decode_strings, literal::decode(&hit.data)) {
+            (true, Some(text)) => {
+                write_escaped(&mut self.writer, text.as_bytes(), false)?;
+                self.writer.write_all(&[CHAR_NEWLINE])
+            }
+            _ => self.write_hit(hit, 0, &hit.data),
+        }
+    }
+
+    fn on_repaired(&mut self, hit: &Hit, repaired: &[u8]) -> io::Result<()> {
+        let spooled = hit
+            .spooled
+            .as_ref()
+            .map_or(0, |spooled| spooled.repaired_len());
+        self.write_hit(hit, spooled, repaired)
+    }
+}
+
+/// A sink that writes a status report for every corrupted JSON string.
+///
+/// Status reports are comma-separated CSVs with the following fields:
+///
+/// ```text
+/// status,start,end,partial_end[,pages][,score][,parent][,encoding][,object,class][,replacements]
+/// ```
+///
+/// where:
+/// * `status` is either "corrupted", "exhausted", "too_deep", "too_large",
+///   "salvaged", "suffix_recovered", "page_boundary", "invalid_unicode",
+///   "completed", or "string".
+/// * (`start`, `end`) is the position of the JSON string within the byte
+///   stream, last character included.
+/// * `partial_end` is the position of the last character where the JSON
+///   string could have ended.
+/// * `pages` are the page boundaries that the JSON string crosses, separated
+///   by semicolons, if the report has a page size (see
+///   [`CsvReport::page_size`]).
+/// * `score` is the score of the JSON string, if the carver has scored it
+///   (see [`Hit::score`]).
+/// * `parent` is the position of the JSON string that the JSON string is
+///   nested within, if any, if the report lists parents (see
+///   [`CsvReport::parents`]).
+/// * `encoding` is the encoding that the JSON string was found in, e.g.,
+///   "utf-16le", if the carver has carved other encodings than UTF-8 (see
+///   [`Hit::encoding`]).
+/// * (`object`, `class`) are the ID, in hex, and the class of the object that
+///   the JSON string was found in, if the carver has carved a Java heap dump
+///   (see [`Hit::object`]).
+/// * `replacements` is the number of replacements that sanitizing the JSON
+///   string makes, if the report lists them (see [`CsvReport::sanitize`]).
+pub struct CsvReport<W> {
+    writer: W,
+    report_all: bool,
+    page_size: Option<usize>,
+    parents: bool,
+    sanitize: Option<Sanitize>,
+}
+
+impl<W: Write> CsvReport<W> {
+    /// Create a sink that writes reports to `writer`. If `report_all` is set,
+    /// completed JSON strings are reported as well.
+    pub fn new(writer: W, report_all: bool) -> Self {
+        CsvReport {
+            writer,
+            report_all,
+            page_size: None,
+            parents: false,
+            sanitize: None,
+        }
+    }
+
+    /// Configure whether to list the page boundaries that every JSON string
+    /// crosses, for pages of `size` bytes (see [`Hit::page_boundaries`]).
+    pub fn page_size(&mut self, size: Option<usize>) {
+        self.page_size = size;
+    }
+
+    /// Configure whether to list the position of the JSON string that every
+    /// JSON string is nested within, if the carver carves exhaustively (see
+    /// [`Hit::parent`]).
+    pub fn parents(&mut self, opt: bool) {
+        self.parents = opt;
+    }
+
+    /// Configure whether to list the number of replacements that sanitizing
+    /// every JSON string makes, i.e., the number of invalid UTF-8 sequences
+    /// and unpaired surrogate escapes that [`JsonLines::sanitize`] replaces
+    /// in it, or in its repaired version, if any. A Latin-1 reinterpretation
+    /// replaces every byte of an invalid UTF-8 sequence.
+    pub fn sanitize(&mut self, mode: Option<Sanitize>) {
+        self.sanitize = mode;
+    }
+
+    /// Return the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    fn write_report(&mut self, hit: &Hit) -> io::Result<()> {
+        write!(
+            self.writer,
+            "{},{},{},{}",
+            hit.status.as_str(),
+            hit.start,
+            hit.end,
+            hit.partial_end
+        )?;
+        if let Some(size) = self.page_size {
+            self.writer.write_all(b",")?;
+            for (i, boundary) in hit.page_boundaries(size).enumerate() {
+                if i > 0 {
+                    self.writer.write_all(b";")?;
+                }
+                write!(self.writer, "{boundary}")?;
+            }
+        }
+        if let Some(score) = hit.score {
+            write!(self.writer, ",{score}")?;
+        }
+        if self.parents {
+            self.writer.write_all(b",")?;
+            if let Some(parent) = hit.parent {
+                write!(self.writer, "{parent}")?;
+            }
+        }
+        if let Some(encoding) = hit.encoding {
+            write!(self.writer, ",{}", encoding.as_str())?;
+        }
+        if let Some(object) = &hit.object {
+            write!(self.writer, ",0x{:x},{}", object.id, object.class)?;
+        }
+        if let Some(mode) = self.sanitize {
+            write!(self.writer, ",{}", sanitize::replacements(hit, mode)?)?;
+        }
+        self.writer.write_all(&[CHAR_NEWLINE])
+    }
+}
+
+impl<W: Write> Sink for CsvReport<W> {
+    fn on_completed(&mut self, hit: &Hit) -> io::Result<()> {
+        match self.report_all {
+            true => self.write_report(hit),
+            false => Ok(()),
+        }
+    }
+
+    fn on_string(&mut self, hit: &Hit) -> io::Result<()> {
+        self.on_completed(hit)
+    }
+
+    fn on_corrupted(&mut self, hit: &Hit) -> io::Result<()> {
+        self.write_report(hit)
+    }
+
+    fn on_exhausted(&mut self, hit: &Hit) -> io::Result<()> {
+        self.write_report(hit)
+    }
+
+    fn on_too_deep(&mut self, hit: &Hit) -> io::Result<()> {
+        self.write_report(hit)
+    }
+
+    fn on_too_large(&mut self, hit: &Hit) -> io::Result<()> {
+        self.write_report(hit)
+    }
+
+    fn on_salvaged(&mut self, hit: &Hit) -> io::Result<()> {
+        self.write_report(hit)
+    }
+
+    fn on_suffix_recovered(&mut self, hit: &Hit) -> io::Result<()> {
+        self.write_report(hit)
+    }
+}
+
+/// Write `bytes` as a JSON string, quotes included. Quotes, backslashes and
+/// control characters are escaped, and so is every byte that is not valid
+/// UTF-8, as the Latin-1 character that it stands for, so that the JSON string
+/// is valid whatever the bytes are.
+pub(crate) fn write_json_string<W: Write + ?Sized>(writer: &mut W, bytes: &[u8]) -> io::Result<()> {
+    writer.write_all(&[CHAR_QUOT_MARK])?;
+    write_escaped(writer, bytes, true)?;
+    writer.write_all(&[CHAR_QUOT_MARK])
+}
+
+/// Write `bytes` with their control characters and invalid UTF-8 escaped,
+/// and their quotes and backslashes as well, if `quotes` is set.
+fn write_escaped<W: Write + ?Sized>(writer: &mut W, bytes: &[u8], quotes: bool) -> io::Result<()> {
+    for chunk in bytes.utf8_chunks() {
+        let valid = chunk.valid().as_bytes();
+        let mut written = 0;
+        for (i, &b) in valid.iter().enumerate() {
+            let escape = match b {
+                CHAR_QUOT_MARK | CHAR_ESCAPE if quotes => b,
+                CHAR_NEWLINE => CHAR_ESC_LINE_FEED,
+                CHAR_CARRIAGE_RETURN => CHAR_ESC_CARRIAGE_RETURN,
+                CHAR_TAB => CHAR_ESC_TAB,
+                ..CHAR_SPACE => CHAR_U,
+                _ => continue,
+            };
+            writer.write_all(&valid[written..i])?;
+            match escape {
+                CHAR_U => write!(writer, "\\u{b:04x}")?,
+                escape => writer.write_all(&[CHAR_ESCAPE, escape])?,
+            }
+            written = i + 1;
+        }
+        writer.write_all(&valid[written..])?;
+        for b in chunk.invalid() {
+            write!(writer, "\\u{b:04x}")?;
+        }
+    }
+    Ok(())
+}
+
+/// A sink that writes the ranked repair candidates of every JSON string that
+/// did not complete, one per line.
+///
+/// Every line is a JSON object with the following fields:
+///
+/// ```text
+/// {"start":2,"end":9,"rank":1,"strategy":"skip_corrupted","retained":6,"edits":[{"drop":[6,7]}],"repaired":"[1, 2]"}
+/// ```
+///
+/// where:
+/// * (`start`, `end`) is the part of the byte stream that the candidate
+///   covers, last character included.
+/// * `rank` is the rank of the candidate, starting from 1 for the best one.
+/// * `strategy` is either "truncate", "close_string", "drop_trailing_comma",
+///   or "skip_corrupted".
+/// * `retained` is the number of bytes of the byte stream that it keeps.
+/// * `edits` are the edits that turn the bytes that it covers into the
+///   repaired version, in the order of their positions. They are either
+///   `{"drop":[start,end]}`, with the positions of the dropped bytes, last
+///   byte included, or `{"insert":[at,"bytes"]}`, with the bytes that were
+///   inserted right before position `at`.
+/// * `repaired` is the repaired JSON string, as a JSON string.
+pub struct RepairLog<W> {
+    writer: W,
+}
+
+impl<W: Write> RepairLog<W> {
+    /// Create a sink that writes repair candidates to `writer`.
+    pub fn new(writer: W) -> Self {
+        RepairLog { writer }
+    }
+
+    /// Return the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    fn write_edit(&mut self, edit: &Edit) -> io::Result<()> {
+        match edit {
+            Edit::Drop { start, end } => write!(self.writer, "{{\"drop\":[{start},{end}]}}"),
+            Edit::Insert { at, bytes } => {
+                write!(self.writer, "{{\"insert\":[{at},")?;
+                write_json_string(&mut self.writer, bytes)?;
+                write!(self.writer, "]}}")
+            }
+        }
+    }
+}
+
+impl<W: Write> Sink for RepairLog<W> {
+    fn on_candidates(&mut self, hit: &Hit, candidates: &[Candidate]) -> io::Result<()> {
+        for (rank, candidate) in candidates.iter().enumerate() {
+            write!(
+                self.writer,
+                "{{\"start\":{},\"end\":{},\"rank\":{},\"strategy\":\"{}\",\"retained\":{},\"edits\":[",
+                hit.start,
+                candidate.end,
+                rank + 1,
+                candidate.strategy.as_str(),
+                candidate.retained,
+            )?;
+            for (i, edit) in candidate.edits.iter().enumerate() {
+                if i > 0 {
+                    self.writer.write_all(b",")?;
+                }
+                self.write_edit(edit)?;
+            }
+            self.writer.write_all(b"],\"repaired\":")?;
+            write_json_string(&mut self.writer, &candidate.repaired)?;
+            self.writer.write_all(b"}\n")?;
+        }
+        Ok(())
+    }
+}
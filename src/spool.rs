@@ -0,0 +1,116 @@
+//! Spool files for JSON strings that are too large to keep in memory.
+//!
+//! When a JSON string spans multiple buffers of the reader, the carver has to
+//! copy its bytes, since the buffers are reused. If the JSON string grows past
+//! the spool threshold of the carver, the copied bytes are moved to a
+//! temporary file instead. The spool file is reused for every JSON string, so
+//! discarding the bytes of a corrupted one is simply a matter of rewinding it.
+
+use std::fmt;
+use std::fs::File;
+use std::io;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::ptr;
+
+/// The temporary file that holds the first bytes of the current JSON string.
+/// It is created the first time it's needed.
+#[derive(Debug, Default)]
+pub(crate) struct Spool {
+    file: Option<File>,
+}
+
+impl Spool {
+    /// Append the bytes of the current JSON string to the spool file. If
+    /// `rewind` is set, the bytes of the previous JSON string are discarded
+    /// first.
+    pub(crate) fn write(&mut self, bytes: &[u8], rewind: bool) -> io::Result<()> {
+        let file = match &mut self.file {
+            Some(file) => file,
+            None => self.file.insert(tempfile::tempfile()?),
+        };
+        if rewind {
+            file.rewind()?;
+        }
+        file.write_all(bytes)
+    }
+
+    pub(crate) fn file(&self) -> Option<&File> {
+        self.file.as_ref()
+    }
+}
+
+/// The first bytes of a JSON string that were moved to a spool file, because
+/// the JSON string grew past the spool threshold of the carver (see
+/// [`crate::Carver::spool_threshold`]).
+///
+/// The spool file is reused for the next JSON string, so the bytes can be
+/// read only while the hit is passed to a [`crate::Sink`].
+#[derive(Clone, Copy)]
+pub struct Spooled<'a> {
+    file: &'a File,
+    // The position of the bytes in the spool file.
+    offset: usize,
+    len: usize,
+    repaired_len: usize,
+}
+
+impl<'a> Spooled<'a> {
+    pub(crate) fn new(file: &'a File, offset: usize, len: usize, repaired_len: usize) -> Self {
+        Spooled {
+            file,
+            offset,
+            len,
+            repaired_len,
+        }
+    }
+
+    /// The number of spooled bytes.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether there are no spooled bytes. Always false, since the carver
+    /// spools JSON strings only if they are large.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The number of spooled bytes that the repaired version of the JSON
+    /// string starts with. It may be less than [`Spooled::len`], if the JSON
+    /// string had to be truncated.
+    pub fn repaired_len(&self) -> usize {
+        self.repaired_len
+    }
+
+    /// Copy the first `n` spooled bytes to `writer`.
+    pub fn copy_to<W: Write + ?Sized>(&self, n: usize, writer: &mut W) -> io::Result<()> {
+        let mut file = self.file;
+        file.seek(SeekFrom::Start(self.offset as u64))?;
+        let copied = io::copy(&mut file.take(n as u64), writer)?;
+        match copied == n as u64 {
+            true => Ok(()),
+            false => Err(io::ErrorKind::UnexpectedEof.into()),
+        }
+    }
+}
+
+impl fmt::Debug for Spooled<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Spooled")
+            .field("offset", &self.offset)
+            .field("len", &self.len)
+            .field("repaired_len", &self.repaired_len)
+            .finish()
+    }
+}
+
+impl PartialEq for Spooled<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        ptr::eq(self.file, other.file)
+            && self.offset == other.offset
+            && self.len == other.len
+            && self.repaired_len == other.repaired_len
+    }
+}
+
+impl Eq for Spooled<'_> {}
@@ -0,0 +1,302 @@
+//! Recover JSON strings whose beginning has been overwritten.
+//!
+//! The carver starts JSON strings only at opening brackets, so it cannot find
+//! the second half of a JSON string, e.g., `"body": "text"}, {"id": 5}]}`.
+//! Such a suffix ends with closing brackets that no opening bracket matches.
+//! Starting from the last of them, we scan the bytes backwards for as long as
+//! they can be the end of a JSON string, and then re-open the brackets that
+//! are missing. Objects whose keys are missing get an empty key instead, e.g.,
+//! the above suffix becomes `{"":[{"body": "text"}, {"id": 5}]}`.
+
+use crate::{
+    CHAR_COLON, CHAR_COMMA, CHAR_DECIMAL, CHAR_ESCAPE, CHAR_EXP_LOWER, CHAR_EXP_UPPER, CHAR_MINUS,
+    CHAR_PLUS, CHAR_QUOT_MARK, CHAR_RIGHT_CURLY_BRACKET, CHAR_RIGHT_SQUARE_BRACKET, CHAR_U,
+    CHAR_ZERO, byte_can_escape, byte_needs_escape, is_whitespace,
+};
+
+/// The default number of bytes that the carver looks back at, when it
+/// recovers the suffix of a JSON string.
+pub const DEFAULT_SUFFIX_WINDOW: usize = 1 << 20; // 1MiB
+
+/// What the backward scan expects to find next, i.e., what precedes the
+/// bytes that it has scanned so far.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Expect {
+    /// A value, or the opening bracket of an empty array or object.
+    ValueOrOpen,
+    /// A value, right before a comma.
+    Value,
+    /// A comma, or the opening bracket, right before an array element or an
+    /// object member.
+    CommaOrOpen,
+    /// A colon, right before the value of an object member.
+    Colon,
+    /// A key, right before a colon.
+    Key,
+}
+
+/// The part of a buffer that can be the end of a JSON string.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct Suffix {
+    /// The position where the suffix starts.
+    pub(crate) start: usize,
+    /// The bytes that are missing from the start of the JSON string, i.e.,
+    /// the opening brackets and the keys of the objects.
+    pub(crate) prefix: Vec<u8>,
+}
+
+fn opening(closer: u8) -> u8 {
+    closer - 0x02
+}
+
+/// Scan `buf` backwards, starting from the unmatched closing bracket at
+/// `end`, and return the longest suffix that can be turned into a
+/// structurally valid JSON string, if any.
+///
+/// A suffix must hold at least one value, so that it's worth recovering.
+pub(crate) fn scan_suffix(buf: &[u8], end: usize) -> Option<Suffix> {
+    // The closing brackets whose opening brackets have not been found yet.
+    let mut stack = vec![buf[end]];
+    let mut expect = Expect::ValueOrOpen;
+    let mut best = None;
+    let mut i = end;
+
+    loop {
+        while i > 0 && is_whitespace(buf[i - 1]) {
+            i -= 1;
+        }
+        if i == 0 {
+            break;
+        }
+        let b = buf[i - 1];
+        let top = *stack.last().unwrap();
+        match (expect, b) {
+            (Expect::ValueOrOpen | Expect::CommaOrOpen, _) if b == opening(top) => {
+                i -= 1;
+                stack.pop();
+                let Some(&top) = stack.last() else {
+                    // The JSON string is complete.
+                    best = Some(Suffix {
+                        start: i,
+                        prefix: vec![],
+                    });
+                    break;
+                };
+                expect = after_value(top);
+            }
+            (
+                Expect::ValueOrOpen | Expect::Value,
+                CHAR_RIGHT_SQUARE_BRACKET | CHAR_RIGHT_CURLY_BRACKET,
+            ) => {
+                i -= 1;
+                stack.push(b);
+                expect = Expect::ValueOrOpen;
+            }
+            (Expect::ValueOrOpen | Expect::Value, _) => match scalar_start(buf, i) {
+                Some(start) => {
+                    i = start;
+                    expect = after_value(top);
+                }
+                None => break,
+            },
+            (Expect::Key, CHAR_QUOT_MARK) => match string_start(buf, i) {
+                Some(start) => {
+                    i = start;
+                    expect = Expect::CommaOrOpen;
+                }
+                None => break,
+            },
+            (Expect::Colon, CHAR_COLON) => {
+                i -= 1;
+                expect = Expect::Key;
+            }
+            (Expect::CommaOrOpen, CHAR_COMMA) => {
+                i -= 1;
+                expect = Expect::Value;
+            }
+            _ => break,
+        }
+
+        // The suffix can start right before an array element or an object
+        // member, or right before the value of an object member, whose key
+        // has to be filled in.
+        let missing_key = match expect {
+            Expect::CommaOrOpen => false,
+            Expect::Colon => true,
+            _ => continue,
+        };
+        best = Some(Suffix {
+            start: i,
+            prefix: reopen(&stack, missing_key),
+        });
+    }
+    best
+}
+
+/// Return the bytes that re-open the brackets in `stack`. The objects that
+/// hold the inner brackets get an empty key for them, and so does the
+/// innermost object if `missing_key` is set.
+fn reopen(stack: &[u8], missing_key: bool) -> Vec<u8> {
+    let mut prefix = vec![];
+    for (i, &closer) in stack.iter().enumerate() {
+        prefix.push(opening(closer));
+        let innermost = i == stack.len() - 1;
+        if closer == CHAR_RIGHT_CURLY_BRACKET && (!innermost || missing_key) {
+            prefix.extend_from_slice(br#""":"#);
+        }
+    }
+    prefix
+}
+
+/// What the backward scan expects after it has scanned a value within `top`.
+fn after_value(top: u8) -> Expect {
+    match top {
+        CHAR_RIGHT_SQUARE_BRACKET => Expect::CommaOrOpen,
+        _ => Expect::Colon,
+    }
+}
+
+/// Return the start of the string, number or literal that ends right before
+/// `end`, if any.
+fn scalar_start(buf: &[u8], end: usize) -> Option<usize> {
+    match buf[end - 1] {
+        CHAR_QUOT_MARK => string_start(buf, end),
+        b if b.is_ascii_digit() => number_start(buf, end),
+        _ => [&b"true"[..], b"false", b"null"]
+            .iter()
+            .find(|literal| buf[..end].ends_with(literal))
+            .map(|literal| end - literal.len()),
+    }
+}
+
+/// Return whether the quote at `pos` is escaped, i.e., whether an odd number
+/// of backslashes precede it.
+fn is_escaped(buf: &[u8], pos: usize) -> bool {
+    let escapes = buf[..pos]
+        .iter()
+        .rev()
+        .take_while(|&&b| b == CHAR_ESCAPE)
+        .count();
+    escapes % 2 == 1
+}
+
+/// Return the start of the string that ends right before `end`, if any.
+fn string_start(buf: &[u8], end: usize) -> Option<usize> {
+    let close = end - 1;
+    if is_escaped(buf, close) {
+        return None;
+    }
+    let mut open = close;
+    loop {
+        open = buf[..open].iter().rposition(|&b| b == CHAR_QUOT_MARK)?;
+        if !is_escaped(buf, open) {
+            break;
+        }
+    }
+
+    // Make sure that the contents of the string are valid.
+    let mut contents = buf[open + 1..close].iter();
+    while let Some(&b) = contents.next() {
+        match b {
+            CHAR_ESCAPE => match contents.next() {
+                Some(&CHAR_U) => {
+                    for _ in 0..4 {
+                        if !contents.next().is_some_and(u8::is_ascii_hexdigit) {
+                            return None;
+                        }
+                    }
+                }
+                Some(&b) if byte_can_escape(b) => (),
+                _ => return None,
+            },
+            b if byte_needs_escape(b) => return None,
+            _ => (),
+        }
+    }
+    Some(open)
+}
+
+/// Return the start of the number that ends right before `end`, if any.
+fn number_start(buf: &[u8], end: usize) -> Option<usize> {
+    let len = buf[..end]
+        .iter()
+        .rev()
+        .take_while(|&&b| {
+            b.is_ascii_digit()
+                || matches!(
+                    b,
+                    CHAR_MINUS | CHAR_PLUS | CHAR_DECIMAL | CHAR_EXP_LOWER | CHAR_EXP_UPPER
+                )
+        })
+        .count();
+    let start = end - len;
+    match is_number(&buf[start..end]) {
+        true => Some(start),
+        false => None,
+    }
+}
+
+/// Return whether `buf` is a valid JSON number.
+fn is_number(buf: &[u8]) -> bool {
+    let digits = |buf: &[u8]| buf.iter().take_while(|b| b.is_ascii_digit()).count();
+    let mut rest = buf.strip_prefix(&[CHAR_MINUS]).unwrap_or(buf);
+    match digits(rest) {
+        0 => return false,
+        n if n > 1 && rest[0] == CHAR_ZERO => return false,
+        n => rest = &rest[n..],
+    }
+    if let Some(frac) = rest.strip_prefix(&[CHAR_DECIMAL]) {
+        match digits(frac) {
+            0 => return false,
+            n => rest = &frac[n..],
+        }
+    }
+    let exp = match rest.first() {
+        Some(&CHAR_EXP_LOWER | &CHAR_EXP_UPPER) => &rest[1..],
+        _ => return rest.is_empty(),
+    };
+    let exp = match exp.first() {
+        Some(&CHAR_MINUS | &CHAR_PLUS) => &exp[1..],
+        _ => exp,
+    };
+    let n = digits(exp);
+    n > 0 && n == exp.len()
+}
+
+/// Append `bytes` to the bytes that the carver has consumed recently, and
+/// keep at least the last `window` of them.
+pub(crate) fn remember(history: &mut Vec<u8>, bytes: &[u8], window: usize) {
+    history.extend_from_slice(&bytes[bytes.len().saturating_sub(window)..]);
+    // Drop the old bytes in bulk, to avoid moving the rest too often.
+    if history.len() > 2 * window {
+        history.drain(..history.len() - window);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use super::*;
+
+    #[rstest]
+    #[case(br#"xx"body": "text"}, {"id": 5}]}"#, Some((2, r#"{"":[{"#)))]
+    #[case(br#"[1, 2]]"#, Some((0, "[")))]
+    #[case(br#"{"a": 1}"#, Some((0, "")))]
+    #[case(br#"x"a\"b": [true, null, -1.5e3]}"#, Some((1, "{")))]
+    #[case(br#"x 1, "b": 2}"#, Some((2, r#"{"":"#)))]
+    #[case(br#"{"a": 1}}"#, Some((0, r#"{"":"#)))]
+    #[case(br#", 2, 3]"#, Some((2, "[")))]
+    #[case(br#"x"unterminated: 1}"#, Some((16, r#"{"":"#)))]
+    #[case(br#"]"#, None)]
+    #[case(br#"x]"#, None)]
+    #[case(br#"01]"#, None)]
+    fn test_scan_suffix(#[case] buf: &[u8], #[case] expected: Option<(usize, &str)>) {
+        let suffix = scan_suffix(buf, buf.len() - 1);
+        let expected = expected.map(|(start, prefix)| Suffix {
+            start,
+            prefix: prefix.as_bytes().to_vec(),
+        });
+        assert_eq!(suffix, expected);
+    }
+}